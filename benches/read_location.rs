@@ -0,0 +1,15 @@
+//! Reading the location of an image, the work done for each file in a scan.
+//! Run with `cargo bench`.
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use img_coords::file_location::FileLocation;
+
+fn read_location(c: &mut Criterion) {
+    for file in ["test_files/sunrise.jpg", "test_files/sunrise.heic"] {
+        c.bench_function(file, |b| {
+            b.iter(|| FileLocation::from_file(black_box(file)).unwrap())
+        });
+    }
+}
+
+criterion_group!(benches, read_location);
+criterion_main!(benches);
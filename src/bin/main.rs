@@ -1,6 +1,8 @@
 use anyhow::{Context, Result};
 use chrono::{NaiveDate, NaiveDateTime};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use img_coords::config::Config;
+use img_coords::file_location::SidecarMode;
 use img_coords::file_set::FileSet;
 use std::path::{Path, PathBuf};
 
@@ -14,10 +16,45 @@ struct Cli {
     // /// Turn debugging information on
     // #[arg(short, long, action = clap::ArgAction::Count)]
     // debug: u8,
+    /// JSON configuration file [default: img_coords/config.json in the user's config directory]
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+/// The rating, label and keyword filters, read from the image, its XMP sidecar or the
+/// exiftool JSON.
+#[derive(Args)]
+struct DescriptionFilters {
+    /// Keeps only images rated at least this many stars (XMP xmp:Rating)
+    #[arg(long, value_name = "STARS")]
+    min_rating: Option<i32>,
+
+    /// Keeps only images with this color label (XMP xmp:Label); may be repeated
+    #[arg(long, value_name = "LABEL")]
+    label: Vec<String>,
+
+    /// Keeps only images with this keyword; may be repeated, any one matches
+    #[arg(long, value_name = "KEYWORD")]
+    keyword: Vec<String>,
+
+    /// Drops images with this keyword; may be repeated
+    #[arg(long, value_name = "KEYWORD")]
+    exclude_keyword: Vec<String>,
+}
+
+impl DescriptionFilters {
+    fn apply(&self, fs: &mut FileSet) {
+        if let Some(stars) = self.min_rating {
+            fs.set_min_rating(stars);
+        }
+        fs.set_labels(self.label.clone());
+        fs.set_keywords(self.keyword.clone(), self.exclude_keyword.clone());
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// scans a directory tree
@@ -38,11 +75,97 @@ enum Commands {
         #[arg(short, long)]
         thumbnails: bool,
 
-        /// Sets a maximum EXIF timestamp (inclusive) YYYY-MM-DD
+        /// Adds the photographed object's position (EXIF GPSDest*) and the sight line to it
+        #[arg(long)]
+        sight_lines: bool,
+
+        /// Add camera, lens and exposure metadata to the output
+        #[arg(long)]
+        exif_fields: bool,
+
+        /// Keep locations that fail validation (eg (0,0) or void GPS fixes) in the output
+        #[arg(long)]
+        keep_invalid: bool,
+
+        /// Writes locations that fail validation, with the reason, to this JSON file
+        #[arg(long, value_name = "FILE")]
+        quarantine: Option<PathBuf>,
+
+        /// Take the position from XMP sidecars (photo.xmp, photo.jpg.xmp) over the image's own
+        #[arg(long, conflicts_with = "ignore_sidecars")]
+        prefer_sidecars: bool,
+
+        /// Do not read XMP or Google Takeout sidecars; by default they are used for images without a position
+        #[arg(long)]
+        ignore_sidecars: bool,
+
+        /// Sets a maximum EXIF timestamp (inclusive) YYYY-MM-DD, compared in UTC where the time zone is known
         #[arg(short, long)]
         before: Option<String>,
 
-        /// Sets a minimum EXIF timestamp (inclusive) YYYY-MM-DD
+        /// Sets a minimum EXIF timestamp (inclusive) YYYY-MM-DD, compared in UTC where the time zone is known
+        #[arg(short, long)]
+        after: Option<String>,
+
+        /// Drops locations with an estimated GPS error above this many metres
+        #[arg(long, value_name = "METRES")]
+        max_error: Option<f64>,
+
+        /// With --max-error, flags inaccurate locations instead of dropping them
+        #[arg(long, requires = "max_error")]
+        flag_inaccurate: bool,
+
+        /// Also adds the geotagged images of this Lightroom Classic catalog (.lrcat) the scan did not locate
+        #[arg(long, value_name = "FILE")]
+        catalog: Option<PathBuf>,
+
+        #[command(flatten)]
+        filters: DescriptionFilters,
+
+        /// Also reads files with these extensions, eg --ext jpe,jfif; adds to the config file's "extensions"
+        #[arg(long, value_name = "EXT", value_delimiter = ',')]
+        ext: Vec<String>,
+
+        /// Does not read files with these extensions, eg --no-ext mov,mp4; adds to the config file's "excluded_extensions"
+        #[arg(long, value_name = "EXT", value_delimiter = ',')]
+        no_ext: Vec<String>,
+    },
+
+    /// reads the geotagged images of a Lightroom Classic catalog (.lrcat)
+    Catalog {
+        /// The catalog, opened read-only; Lightroom must not have it open
+        #[arg(value_name = "FILE")]
+        catalog: PathBuf,
+
+        /// A file (GeoJSON, KML) to update, ignoring files already in the file
+        #[arg(short, long, value_name = "FILE")]
+        update: Option<PathBuf>,
+
+        /// Specifies the output format [KML, GEOJSON]
+        #[arg(short, long)]
+        format: Option<String>,
+
+        /// Generate thumbnails for GeoJSON
+        #[arg(short, long)]
+        thumbnails: bool,
+
+        /// Adds the photographed object's position (EXIF GPSDest*) and the sight line to it
+        #[arg(long)]
+        sight_lines: bool,
+
+        /// Keep locations that fail validation (eg (0,0) or void GPS fixes) in the output
+        #[arg(long)]
+        keep_invalid: bool,
+
+        /// Writes locations that fail validation, with the reason, to this JSON file
+        #[arg(long, value_name = "FILE")]
+        quarantine: Option<PathBuf>,
+
+        /// Sets a maximum EXIF timestamp (inclusive) YYYY-MM-DD, compared in UTC where the time zone is known
+        #[arg(short, long)]
+        before: Option<String>,
+
+        /// Sets a minimum EXIF timestamp (inclusive) YYYY-MM-DD, compared in UTC where the time zone is known
         #[arg(short, long)]
         after: Option<String>,
     },
@@ -60,19 +183,121 @@ enum Commands {
         /// Generate thumbnails for GeoJSON
         #[arg(short, long)]
         thumbnails: bool,
+
+        /// Adds the photographed object's position (EXIF GPSDest*) and the sight line to it
+        #[arg(long)]
+        sight_lines: bool,
+
+        /// Add camera, lens and exposure metadata to the output
+        #[arg(long)]
+        exif_fields: bool,
+
+        /// Keep locations that fail validation (eg (0,0) or void GPS fixes) in the output
+        #[arg(long)]
+        keep_invalid: bool,
+
+        /// Writes locations that fail validation, with the reason, to this JSON file
+        #[arg(long, value_name = "FILE")]
+        quarantine: Option<PathBuf>,
+
+        /// Take the position from XMP sidecars (photo.xmp, photo.jpg.xmp) over the image's own
+        #[arg(long, conflicts_with = "ignore_sidecars")]
+        prefer_sidecars: bool,
+
+        /// Do not read XMP or Google Takeout sidecars; by default they are used for images without a position
+        #[arg(long)]
+        ignore_sidecars: bool,
+
+        #[command(flatten)]
+        filters: DescriptionFilters,
+
+        /// Also reads files with these extensions, eg --ext jpe,jfif; adds to the config file's "extensions"
+        #[arg(long, value_name = "EXT", value_delimiter = ',')]
+        ext: Vec<String>,
+
+        /// Does not read files with these extensions, eg --no-ext mov,mp4; adds to the config file's "excluded_extensions"
+        #[arg(long, value_name = "EXT", value_delimiter = ',')]
+        no_ext: Vec<String>,
+    },
+
+    /// imports the JSON of `exiftool -j -n -GPS:all -DateTimeOriginal -r DIR` from a file or STDIN
+    ImportExiftool {
+        /// The exiftool JSON file; read from STDIN if not given
+        #[arg(value_name = "FILE")]
+        input: Option<PathBuf>,
+
+        /// A file (GeoJSON, KML) to update, ignoring files already in the file
+        #[arg(short, long, value_name = "FILE")]
+        update: Option<PathBuf>,
+
+        /// Specifies the output format [KML, GEOJSON]
+        #[arg(short, long)]
+        format: Option<String>,
+
+        /// Generate thumbnails for GeoJSON
+        #[arg(short, long)]
+        thumbnails: bool,
+
+        /// Adds the photographed object's position (EXIF GPSDest*) and the sight line to it
+        #[arg(long)]
+        sight_lines: bool,
+
+        /// Keep locations that fail validation (eg (0,0) or void GPS fixes) in the output
+        #[arg(long)]
+        keep_invalid: bool,
+
+        /// Writes locations that fail validation, with the reason, to this JSON file
+        #[arg(long, value_name = "FILE")]
+        quarantine: Option<PathBuf>,
+
+        /// Sets a maximum EXIF timestamp (inclusive) YYYY-MM-DD, compared in UTC where the time zone is known
+        #[arg(short, long)]
+        before: Option<String>,
+
+        /// Sets a minimum EXIF timestamp (inclusive) YYYY-MM-DD, compared in UTC where the time zone is known
+        #[arg(short, long)]
+        after: Option<String>,
+
+        /// Drops locations with an estimated GPS error above this many metres
+        #[arg(long, value_name = "METRES")]
+        max_error: Option<f64>,
+
+        /// With --max-error, flags inaccurate locations instead of dropping them
+        #[arg(long, requires = "max_error")]
+        flag_inaccurate: bool,
+
+        /// Add camera, lens and exposure metadata to the output (needs `-EXIF:all` in the JSON)
+        #[arg(long)]
+        exif_fields: bool,
+
+        #[command(flatten)]
+        filters: DescriptionFilters,
     },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let config = || Config::load(cli.config.as_deref());
     match &cli.command {
         Some(Commands::Scan {
             dir,
             update,
             format,
             thumbnails,
+            sight_lines,
+            exif_fields,
+            keep_invalid,
+            quarantine,
+            prefer_sidecars,
+            ignore_sidecars,
             before,
             after,
+            max_error,
+            flag_inaccurate,
+            catalog,
+            filters,
+            ext,
+            no_ext,
         }) => {
             let root = match dir {
                 Some(dir) => path_to_str(dir)?,
@@ -80,29 +305,133 @@ fn main() -> Result<()> {
             };
             let mut fs = FileSet::default();
             load_update(&mut fs, update)?;
+            fs.set_exif_fields(*exif_fields);
+            fs.set_sidecar_mode(sidecar_mode(*prefer_sidecars, *ignore_sidecars));
             if let Some(date) = before {
                 fs.set_before(parse_date(date, "before")?);
             }
             if let Some(date) = after {
                 fs.set_after(parse_date(date, "after")?);
             }
+            if let Some(metres) = max_error {
+                fs.set_max_error(*metres, *flag_inaccurate);
+            }
+            filters.apply(&mut fs);
+            let (extra, excluded) = config()?.extensions(ext, no_ext);
+            fs.set_extensions(extra, excluded);
+            fs.set_keep_invalid(*keep_invalid);
             fs.scan_tree(root)?;
+            if let Some(catalog) = catalog {
+                fs.load_lightroom_catalog(path_to_str(catalog)?)?;
+            }
+            write_quarantine(&fs, quarantine)?;
             if *thumbnails {
                 fs.generate_missing_thumbnails();
             }
+            fs.set_sight_lines(*sight_lines);
+            fs.output(format)?;
+        }
+        Some(Commands::Catalog {
+            catalog,
+            update,
+            format,
+            thumbnails,
+            sight_lines,
+            keep_invalid,
+            quarantine,
+            before,
+            after,
+        }) => {
+            let mut fs = FileSet::default();
+            load_update(&mut fs, update)?;
+            if let Some(date) = before {
+                fs.set_before(parse_date(date, "before")?);
+            }
+            if let Some(date) = after {
+                fs.set_after(parse_date(date, "after")?);
+            }
+            fs.set_keep_invalid(*keep_invalid);
+            fs.load_lightroom_catalog(path_to_str(catalog)?)?;
+            write_quarantine(&fs, quarantine)?;
+            if *thumbnails {
+                fs.generate_missing_thumbnails();
+            }
+            fs.set_sight_lines(*sight_lines);
             fs.output(format)?;
         }
         Some(Commands::Import {
             update,
             format,
             thumbnails,
+            sight_lines,
+            exif_fields,
+            keep_invalid,
+            quarantine,
+            prefer_sidecars,
+            ignore_sidecars,
+            filters,
+            ext,
+            no_ext,
         }) => {
             let mut fs = FileSet::default();
             load_update(&mut fs, update)?;
+            fs.set_exif_fields(*exif_fields);
+            fs.set_sidecar_mode(sidecar_mode(*prefer_sidecars, *ignore_sidecars));
+            filters.apply(&mut fs);
+            let (extra, excluded) = config()?.extensions(ext, no_ext);
+            fs.set_extensions(extra, excluded);
+            fs.set_keep_invalid(*keep_invalid);
             fs.import_files();
+            write_quarantine(&fs, quarantine)?;
+            if *thumbnails {
+                fs.generate_missing_thumbnails();
+            }
+            fs.set_sight_lines(*sight_lines);
+            fs.output(format)?;
+        }
+        Some(Commands::ImportExiftool {
+            input,
+            update,
+            format,
+            thumbnails,
+            sight_lines,
+            keep_invalid,
+            quarantine,
+            before,
+            after,
+            max_error,
+            flag_inaccurate,
+            exif_fields,
+            filters,
+        }) => {
+            let data = match input {
+                Some(input) => std::fs::read_to_string(input)
+                    .with_context(|| format!("Failed to read file '{}'", input.display()))?,
+                None => {
+                    std::io::read_to_string(std::io::stdin()).context("Failed to read STDIN")?
+                }
+            };
+            let mut fs = FileSet::default();
+            load_update(&mut fs, update)?;
+            if let Some(date) = before {
+                fs.set_before(parse_date(date, "before")?);
+            }
+            if let Some(date) = after {
+                fs.set_after(parse_date(date, "after")?);
+            }
+            if let Some(metres) = max_error {
+                fs.set_max_error(*metres, *flag_inaccurate);
+            }
+            fs.set_exif_fields(*exif_fields);
+            filters.apply(&mut fs);
+            fs.set_keep_invalid(*keep_invalid);
+            fs.import_exiftool_json(&data)
+                .context("Could not read exiftool JSON")?;
+            write_quarantine(&fs, quarantine)?;
             if *thumbnails {
                 fs.generate_missing_thumbnails();
             }
+            fs.set_sight_lines(*sight_lines);
             fs.output(format)?;
         }
         None => {} // Never gets called
@@ -120,6 +449,21 @@ fn load_update(fs: &mut FileSet, update: &Option<PathBuf>) -> Result<()> {
     Ok(())
 }
 
+fn write_quarantine(fs: &FileSet, quarantine: &Option<PathBuf>) -> Result<()> {
+    if let Some(filename) = quarantine {
+        fs.write_quarantine_report(path_to_str(filename)?)?;
+    }
+    Ok(())
+}
+
+fn sidecar_mode(prefer: bool, ignore: bool) -> SidecarMode {
+    match (prefer, ignore) {
+        (true, _) => SidecarMode::Prefer,
+        (_, true) => SidecarMode::Ignore,
+        _ => SidecarMode::Fallback,
+    }
+}
+
 fn parse_date(date: &str, which: &str) -> Result<NaiveDateTime> {
     const DATE_FORMAT: &str = "%Y-%m-%d";
     NaiveDate::parse_from_str(date, DATE_FORMAT)
@@ -0,0 +1,128 @@
+use crate::file_location::{
+    exif_rational, exif_string, property_f64, property_string, property_u32, push_property,
+};
+use exif::{Exif, In, Tag};
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+
+/// Camera, lens and exposure metadata. Only read when requested (`--exif-fields`), to keep
+/// the default output small.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CameraInfo {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub lens_model: Option<String>,
+    pub focal_length: Option<f64>,      // mm
+    pub focal_length_35mm: Option<u32>, // mm, 35mm film equivalent
+    pub exposure_time: Option<f64>,     // seconds
+    pub f_number: Option<f64>,
+    pub iso: Option<u32>,
+    pub width: Option<u32>, // pixels
+    pub height: Option<u32>,
+}
+
+impl CameraInfo {
+    /// Returns `None` if the image carries none of the fields.
+    pub(crate) fn from_exif(exif: &Exif) -> Option<Self> {
+        let string = |tag: Tag| exif_string(exif, tag);
+        let uint = |tag: Tag| exif.get_field(tag, In::PRIMARY)?.value.get_uint(0);
+        let rational = |tag: Tag| exif_rational(exif, tag);
+        let ret = Self {
+            make: string(Tag::Make),
+            model: string(Tag::Model),
+            lens_model: string(Tag::LensModel),
+            focal_length: rational(Tag::FocalLength),
+            focal_length_35mm: uint(Tag::FocalLengthIn35mmFilm),
+            exposure_time: rational(Tag::ExposureTime),
+            f_number: rational(Tag::FNumber),
+            iso: uint(Tag::PhotographicSensitivity),
+            width: uint(Tag::PixelXDimension).or_else(|| uint(Tag::ImageWidth)),
+            height: uint(Tag::PixelYDimension).or_else(|| uint(Tag::ImageLength)),
+        };
+        (ret != Self::default()).then_some(ret)
+    }
+
+    /// One property per field, under the field's name; the exposure time in seconds,
+    /// not as a fraction.
+    pub(crate) fn properties(&self) -> Vec<(&'static str, serde_json::Value)> {
+        let mut ret = Vec::new();
+        push_property(&mut ret, "make", self.make.as_ref());
+        push_property(&mut ret, "model", self.model.as_ref());
+        push_property(&mut ret, "lens_model", self.lens_model.as_ref());
+        push_property(&mut ret, "focal_length", self.focal_length);
+        push_property(&mut ret, "focal_length_35mm", self.focal_length_35mm);
+        push_property(&mut ret, "exposure_time", self.exposure_time);
+        push_property(&mut ret, "f_number", self.f_number);
+        push_property(&mut ret, "iso", self.iso);
+        push_property(&mut ret, "width", self.width);
+        push_property(&mut ret, "height", self.height);
+        ret
+    }
+
+    pub(crate) fn from_properties(properties: &Map<String, serde_json::Value>) -> Option<Self> {
+        let ret = Self {
+            make: property_string(properties, "make"),
+            model: property_string(properties, "model"),
+            lens_model: property_string(properties, "lens_model"),
+            focal_length: property_f64(properties, "focal_length"),
+            focal_length_35mm: property_u32(properties, "focal_length_35mm"),
+            exposure_time: property_f64(properties, "exposure_time"),
+            f_number: property_f64(properties, "f_number"),
+            iso: property_u32(properties, "iso"),
+            width: property_u32(properties, "width"),
+            height: property_u32(properties, "height"),
+        };
+        (ret != Self::default()).then_some(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_exif() {
+        let file = std::fs::File::open("test_files/sunrise.jpg").unwrap();
+        let exif = exif::Reader::new()
+            .read_from_container(&mut std::io::BufReader::new(file))
+            .unwrap();
+        let ci = CameraInfo::from_exif(&exif).unwrap();
+        assert_eq!(ci.make, Some("Google".to_string()));
+        assert_eq!(ci.model, Some("Pixel 8 Pro".to_string()));
+        assert_eq!(
+            ci.lens_model,
+            Some("Pixel 8 Pro back camera 18.0mm f/2.8".to_string())
+        );
+        assert_eq!(ci.focal_length, Some(18.0));
+        assert_eq!(ci.focal_length_35mm, Some(221));
+        assert_eq!(ci.exposure_time, Some(0.012358));
+        assert_eq!(ci.f_number, Some(2.8));
+        assert_eq!(ci.iso, Some(18));
+        assert_eq!(ci.width, Some(4080));
+        assert_eq!(ci.height, Some(3072));
+    }
+
+    /// KML gives every value back as text, and other tools may write numbers that do
+    /// not fit the field
+    #[test]
+    fn test_from_properties_as_text() {
+        let properties = serde_json::json!({
+            "model": "Pixel 8 Pro",
+            "exposure_time": "0.012358",
+            "iso": " 18 ",
+            "width": "-1",
+            "height": 1e12,
+        });
+        let ci = CameraInfo::from_properties(properties.as_object().unwrap()).unwrap();
+        assert_eq!(ci.model.as_deref(), Some("Pixel 8 Pro"));
+        assert_eq!(ci.exposure_time, Some(0.012358));
+        assert_eq!(ci.iso, Some(18));
+        assert_eq!(ci.width, None);
+        assert_eq!(ci.height, None);
+    }
+
+    #[test]
+    fn test_from_properties_empty() {
+        assert_eq!(CameraInfo::from_properties(&Map::new()), None);
+    }
+}
@@ -0,0 +1,103 @@
+//! Settings that apply to every run, from a JSON file such as
+//! `{"extensions": ["jpe"], "excluded_extensions": ["mov", "mp4"]}`. It is read from
+//! `--config FILE`, or else from `img_coords/config.json` in the user's configuration
+//! directory if that exists. Command line options take precedence.
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Also read files with these extensions, like `--ext`
+    pub extensions: Vec<String>,
+    /// Do not read files with these extensions, like `--no-ext`
+    pub excluded_extensions: Vec<String>,
+}
+
+impl Config {
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("Invalid configuration")
+    }
+
+    /// The file given, or else the default one; no file at the default location is an
+    /// empty configuration, a missing file that was asked for an error.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_path().filter(|p| p.is_file()) {
+                Some(path) => path,
+                None => return Ok(Self::default()),
+            },
+        };
+        let json = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file '{}'", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("In config file '{}'", path.display()))
+    }
+
+    /// `$XDG_CONFIG_HOME/img_coords/config.json`, falling back to `~/.config`, or
+    /// `%APPDATA%` on Windows.
+    fn default_path() -> Option<PathBuf> {
+        let dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+            .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+        Some(dir.join("img_coords").join("config.json"))
+    }
+
+    /// The extensions to add and to exclude, for `FileSet::set_extensions`. An
+    /// extension given with `--ext` is read even if the configuration excludes it, and
+    /// one given with `--no-ext` is not read even if the configuration adds it.
+    pub fn extensions(&self, ext: &[String], no_ext: &[String]) -> (Vec<String>, Vec<String>) {
+        let is_in = |list: &[String], e: &String| list.iter().any(|x| x.eq_ignore_ascii_case(e));
+        let extra = self
+            .extensions
+            .iter()
+            .filter(|e| !is_in(no_ext, e))
+            .chain(ext)
+            .cloned()
+            .collect();
+        let excluded = self
+            .excluded_extensions
+            .iter()
+            .filter(|e| !is_in(ext, e))
+            .chain(no_ext)
+            .cloned()
+            .collect();
+        (extra, excluded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(s: &[&str]) -> Vec<String> {
+        s.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_from_json() {
+        let config =
+            Config::from_json(r#"{"extensions": ["xyz"], "excluded_extensions": ["MOV"]}"#)
+                .unwrap();
+        assert_eq!(config.extensions, strings(&["xyz"]));
+        assert_eq!(config.excluded_extensions, strings(&["MOV"]));
+        assert_eq!(Config::from_json("{}").unwrap(), Config::default());
+        // A typo should not pass silently
+        assert!(Config::from_json(r#"{"extension": ["xyz"]}"#).is_err());
+        assert!(Config::load(Some(Path::new("test_files/nonexistent.json"))).is_err());
+    }
+
+    #[test]
+    fn test_extensions_command_line_wins() {
+        let config = Config {
+            extensions: strings(&["xyz", "abc"]),
+            excluded_extensions: strings(&["mov", "mp4"]),
+        };
+        let (extra, excluded) = config.extensions(&strings(&["MOV"]), &strings(&["abc"]));
+        assert_eq!(extra, strings(&["xyz", "MOV"]));
+        assert_eq!(excluded, strings(&["mp4", "abc"]));
+    }
+}
@@ -0,0 +1,113 @@
+//! Lenient parsing of the capture times cameras and phones write, and of the ones hidden
+//! in file names.
+use chrono::{NaiveDate, NaiveDateTime};
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// EXIF's `2025:03:06 05:41:42`, and the `-`, `/` and `.` date separators and `T` some
+/// cameras use instead. Time fields may be blank, which EXIF allows for unknown parts.
+/// Sub-seconds and a zone after the time are ignored.
+static RE_DATE_TIME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\d{4})[:\-/.](\d{2})[:\-/.](\d{2})(?:[ T]+([\d ]{2})[:.]([\d ]{2})(?:[:.]([\d ]{2}))?)?")
+        .expect("re_date_time does not compile")
+});
+/// Date and time in phone and camera app file names, eg `IMG_20250306_054142`,
+/// `PXL_20250306_054142123` or `2025-03-06 05.41.42`
+static RE_FILE_NAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|\D)((?:19|20)\d{2})-?(\d{2})-?(\d{2})[_\- T]?(\d{2})[.:\-]?(\d{2})[.:\-]?(\d{2})(?:\d{3})?(?:\D|$)")
+        .expect("re_file_name does not compile")
+});
+
+/// The format of `FileLocation::timestamp`
+pub(crate) const EXIF_FORMAT: &str = "%Y:%m:%d %H:%M:%S";
+
+/// Parses a capture time, ignoring padding NULs and blanks. Unset dates, such as
+/// `0000:00:00 00:00:00` or all blanks, are `None`.
+pub(crate) fn parse_date_time(s: &str) -> Option<NaiveDateTime> {
+    let s = s.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    let captures = RE_DATE_TIME.captures(s)?;
+    let number = |i: usize| -> Option<u32> {
+        match captures.get(i).map(|m| m.as_str().trim()) {
+            None | Some("") => Some(0),
+            Some(digits) => digits.parse().ok(),
+        }
+    };
+    NaiveDate::from_ymd_opt(captures[1].parse().ok()?, number(2)?, number(3)?)?.and_hms_opt(
+        number(4)?,
+        number(5)?,
+        number(6)?,
+    )
+}
+
+/// The date and time in a file name, as phones and camera apps write them.
+pub(crate) fn from_file_name(file_name: &str) -> Option<NaiveDateTime> {
+    let name = std::path::Path::new(file_name).file_name()?.to_str()?;
+    let captures = RE_FILE_NAME.captures(name)?;
+    let number = |i: usize| -> Option<u32> { captures[i].parse().ok() };
+    NaiveDate::from_ymd_opt(captures[1].parse().ok()?, number(2)?, number(3)?)?.and_hms_opt(
+        number(4)?,
+        number(5)?,
+        number(6)?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatted(dt: Option<NaiveDateTime>) -> Option<String> {
+        dt.map(|dt| dt.format(EXIF_FORMAT).to_string())
+    }
+
+    #[test]
+    fn test_parse_date_time() {
+        let expected = Some("2025:03:06 05:41:42".to_string());
+        for s in [
+            "2025:03:06 05:41:42",
+            "2025-03-06 05:41:42",
+            "2025-03-06T05:41:42.125+01:00",
+            "2025/03/06 05:41:42",
+            "2025:03:06 05:41:42\0\0",
+            " 2025:03:06 05:41:42 ",
+        ] {
+            assert_eq!(formatted(parse_date_time(s)), expected, "{s:?}");
+        }
+        assert_eq!(
+            formatted(parse_date_time("2025:03:06 05:41:  ")),
+            Some("2025:03:06 05:41:00".to_string())
+        );
+        assert_eq!(
+            formatted(parse_date_time("2025:03:06")),
+            Some("2025:03:06 00:00:00".to_string())
+        );
+        for s in [
+            "0000:00:00 00:00:00",
+            "    :  :     :  :  ",
+            "",
+            "not a date",
+        ] {
+            assert_eq!(parse_date_time(s), None, "{s:?}");
+        }
+    }
+
+    #[test]
+    fn test_from_file_name() {
+        let expected = Some("2025:03:06 05:41:42".to_string());
+        for name in [
+            "IMG_20250306_054142.jpg",
+            "/photos/PXL_20250306_054142123.jpg",
+            "VID_20250306_054142~2.mp4",
+            "2025-03-06 05.41.42.jpg",
+            "Screenshot_20250306-054142.png",
+        ] {
+            assert_eq!(formatted(from_file_name(name)), expected, "{name}");
+        }
+        for name in [
+            "IMG-20250306-WA0001.jpg",
+            "DSC01234.jpg",
+            "IMG_20251306_054142.jpg",
+        ] {
+            assert_eq!(from_file_name(name), None, "{name}");
+        }
+    }
+}
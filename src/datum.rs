@@ -0,0 +1,177 @@
+//! Conversion of coordinates in the local map datums older cameras and GPS loggers
+//! record in `GPSMapDatum` to WGS 84, with a Helmert transformation between the
+//! earth-centred coordinates of both ellipsoids.
+
+/// Semi-major axis in metres and inverse flattening
+struct Ellipsoid {
+    a: f64,
+    inverse_flattening: f64,
+}
+
+const WGS84: Ellipsoid = Ellipsoid {
+    a: 6_378_137.0,
+    inverse_flattening: 298.257_223_563,
+};
+const BESSEL_1841: Ellipsoid = Ellipsoid {
+    a: 6_377_397.155,
+    inverse_flattening: 299.152_812_8,
+};
+const INTERNATIONAL_1924: Ellipsoid = Ellipsoid {
+    a: 6_378_388.0,
+    inverse_flattening: 297.0,
+};
+const CLARKE_1866: Ellipsoid = Ellipsoid {
+    a: 6_378_206.4,
+    inverse_flattening: 294.978_698_2,
+};
+const AIRY_1830: Ellipsoid = Ellipsoid {
+    a: 6_377_563.396,
+    inverse_flattening: 299.324_964_6,
+};
+
+/// Helmert parameters to WGS 84, position vector convention: translations in metres,
+/// rotations in arc seconds, scale in parts per million.
+struct Helmert {
+    translation: [f64; 3],
+    rotation: [f64; 3],
+    scale: f64,
+}
+
+/// Map datums that can be converted, plus WGS 84 and the datums within a metre or so
+/// of it, which are left alone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Datum {
+    Wgs84,
+    /// Tokyo Datum, used in Japan before JGD2000
+    Tokyo,
+    /// European Datum 1950
+    Ed50,
+    /// North American Datum 1927
+    Nad27,
+    /// Ordnance Survey of Great Britain 1936
+    Osgb36,
+}
+
+impl Datum {
+    /// Recognises the usual spellings, such as `WGS-84`, `TOKYO` or `OSGB 1936`.
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        let name: String = name
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        match name.as_str() {
+            "WGS84" | "WGS1984" | "JGD2000" | "JGD2011" | "ETRS89" | "NAD83" | "GDA94" => {
+                Some(Self::Wgs84)
+            }
+            "TOKYO" | "TOKYODATUM" | "TD" => Some(Self::Tokyo),
+            "ED50" | "EUROPEAN1950" | "EUROPEANDATUM1950" => Some(Self::Ed50),
+            "NAD27" | "NORTHAMERICAN1927" => Some(Self::Nad27),
+            "OSGB36" | "OSGB1936" => Some(Self::Osgb36),
+            _ => None,
+        }
+    }
+
+    /// Regional mean parameters (Japan, western Europe, the contiguous US and Great
+    /// Britain), good to a few metres there.
+    fn parameters(&self) -> Option<(Ellipsoid, Helmert)> {
+        let three = |dx, dy, dz| Helmert {
+            translation: [dx, dy, dz],
+            rotation: [0.0; 3],
+            scale: 0.0,
+        };
+        match self {
+            Self::Wgs84 => None,
+            Self::Tokyo => Some((BESSEL_1841, three(-146.414, 507.337, 680.507))),
+            Self::Ed50 => Some((INTERNATIONAL_1924, three(-87.0, -98.0, -121.0))),
+            Self::Nad27 => Some((CLARKE_1866, three(-8.0, 160.0, 176.0))),
+            Self::Osgb36 => Some((
+                AIRY_1830,
+                Helmert {
+                    translation: [446.448, -125.157, 542.060],
+                    rotation: [0.1502, 0.2470, 0.8421],
+                    scale: -20.4894,
+                },
+            )),
+        }
+    }
+
+    /// Latitude and longitude in WGS 84. Heights in EXIF are above sea level, not the
+    /// ellipsoid, so the conversion is done on the ellipsoid surface.
+    pub(crate) fn to_wgs84(self, latitude: f64, longitude: f64) -> (f64, f64) {
+        let Some((ellipsoid, helmert)) = self.parameters() else {
+            return (latitude, longitude);
+        };
+        let [x, y, z] = to_cartesian(&ellipsoid, latitude, longitude);
+        let [tx, ty, tz] = helmert.translation;
+        let [rx, ry, rz] = helmert.rotation.map(|r| (r / 3600.0).to_radians());
+        let s = 1.0 + helmert.scale * 1e-6;
+        let cartesian = [
+            tx + s * (x - rz * y + ry * z),
+            ty + s * (rz * x + y - rx * z),
+            tz + s * (-ry * x + rx * y + z),
+        ];
+        from_cartesian(&WGS84, cartesian)
+    }
+}
+
+fn eccentricity_squared(ellipsoid: &Ellipsoid) -> f64 {
+    let f = 1.0 / ellipsoid.inverse_flattening;
+    f * (2.0 - f)
+}
+
+fn to_cartesian(ellipsoid: &Ellipsoid, latitude: f64, longitude: f64) -> [f64; 3] {
+    let (lat, lon) = (latitude.to_radians(), longitude.to_radians());
+    let e2 = eccentricity_squared(ellipsoid);
+    let n = ellipsoid.a / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+    [
+        n * lat.cos() * lon.cos(),
+        n * lat.cos() * lon.sin(),
+        n * (1.0 - e2) * lat.sin(),
+    ]
+}
+
+/// Latitude by iteration, which settles well below a millimetre within a few rounds.
+fn from_cartesian(ellipsoid: &Ellipsoid, [x, y, z]: [f64; 3]) -> (f64, f64) {
+    let e2 = eccentricity_squared(ellipsoid);
+    let p = x.hypot(y);
+    let mut lat = z.atan2(p * (1.0 - e2));
+    for _ in 0..10 {
+        let n = ellipsoid.a / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+        lat = (z + e2 * n * lat.sin()).atan2(p);
+    }
+    (lat.to_degrees(), y.atan2(x).to_degrees())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Datum::from_name("WGS-84"), Some(Datum::Wgs84));
+        assert_eq!(Datum::from_name("WGS 84\0"), Some(Datum::Wgs84));
+        assert_eq!(Datum::from_name("TOKYO"), Some(Datum::Tokyo));
+        assert_eq!(Datum::from_name("ed-50"), Some(Datum::Ed50));
+        assert_eq!(Datum::from_name("NAD 27"), Some(Datum::Nad27));
+        assert_eq!(Datum::from_name("OSGB 1936"), Some(Datum::Osgb36));
+        assert_eq!(Datum::from_name("PULKOVO 1942"), None);
+    }
+
+    #[test]
+    fn test_to_wgs84() {
+        assert_eq!(Datum::Wgs84.to_wgs84(45.5, 12.3), (45.5, 12.3));
+        // Tokyo Datum is about 12" off in Tokyo: north-west of WGS 84
+        let (lat, lon) = Datum::Tokyo.to_wgs84(35.6586, 139.7454);
+        assert!((lat - 35.6586 - 0.00323).abs() < 1e-4, "{lat}");
+        assert!((lon - 139.7454 + 0.00321).abs() < 1e-4, "{lon}");
+        // The Airy meridian, 0° in OSGB36, is about 100 m west of WGS 84's
+        let (lat, lon) = Datum::Osgb36.to_wgs84(51.4778, 0.0);
+        assert!((lat - 51.4778).abs() < 1e-3, "{lat}");
+        assert!((lon + 0.0015).abs() < 2e-4, "{lon}");
+        // ED50 is about 100 m south-west of WGS 84 in Italy
+        let (lat, lon) = Datum::Ed50.to_wgs84(45.5, 12.3);
+        assert!((lat - 45.4991).abs() < 1e-4, "{lat}");
+        assert!((lon - 12.2990).abs() < 1e-4, "{lon}");
+    }
+}
@@ -0,0 +1,294 @@
+use crate::file_location::{property_string, property_strings, push_property};
+use crate::isobmff::ByteReader;
+use crate::xmp::{self, Xmp};
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+use std::io::{Read, Seek, SeekFrom};
+
+const PHOTOSHOP_HEADER: &[u8] = b"Photoshop 3.0\0";
+/// The Photoshop image resource holding IPTC-IIM records
+const IPTC_RESOURCE_ID: u16 = 0x0404;
+
+/// Title, caption, keywords, location names, rating and color label that editors add,
+/// from XMP (`dc:`, `photoshop:`, `Iptc4xmpCore:`, `xmp:`) or else the IPTC-IIM block of
+/// a JPEG.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Description {
+    pub title: Option<String>,
+    pub caption: Option<String>,
+    pub keywords: Vec<String>,
+    pub sublocation: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>, // Province or state
+    pub country: Option<String>,
+    #[serde(default)]
+    pub rating: Option<i32>, // Stars, 0 unrated, -1 rejected
+    #[serde(default)]
+    pub label: Option<String>, // Color label, eg "Red"
+}
+
+impl Description {
+    /// Returns `None` if neither the XMP packet nor the IPTC-IIM block of the image has
+    /// any of the fields. XMP takes precedence, field by field.
+    pub(crate) fn from_image<R: Read + Seek>(reader: &mut R, xmp: Option<&Xmp>) -> Option<Self> {
+        let from_xmp = xmp.map(Self::from_xmp).unwrap_or_default();
+        let from_iim = Self::iim_from_jpeg(reader)
+            .map(|iim| Self::from_iim(&iim))
+            .unwrap_or_default();
+        from_xmp.or(from_iim).non_empty()
+    }
+
+    /// Each field of `self`, or of `other` where `self` does not have it.
+    pub(crate) fn or(self, other: Self) -> Self {
+        Self {
+            title: self.title.or(other.title),
+            caption: self.caption.or(other.caption),
+            keywords: match self.keywords.is_empty() {
+                true => other.keywords,
+                false => self.keywords,
+            },
+            sublocation: self.sublocation.or(other.sublocation),
+            city: self.city.or(other.city),
+            state: self.state.or(other.state),
+            country: self.country.or(other.country),
+            rating: self.rating.or(other.rating),
+            label: self.label.or(other.label),
+        }
+    }
+
+    pub(crate) fn non_empty(self) -> Option<Self> {
+        (self != Self::default()).then_some(self)
+    }
+
+    /// Whether the image has `keyword`, ignoring case.
+    pub fn has_keyword(&self, keyword: &str) -> bool {
+        self.keywords
+            .iter()
+            .any(|k| k.eq_ignore_ascii_case(keyword))
+    }
+
+    pub(crate) fn from_xmp(xmp: &Xmp) -> Self {
+        let string = |name: &str| {
+            let s = xmp.get(name)?.trim();
+            (!s.is_empty()).then(|| s.to_string())
+        };
+        Self {
+            title: string("dc:title"),
+            caption: string("dc:description"),
+            keywords: xmp.get_all("dc:subject").to_vec(),
+            sublocation: string("Iptc4xmpCore:Location"),
+            city: string("photoshop:City"),
+            state: string("photoshop:State"),
+            country: string("photoshop:Country"),
+            // Some applications write "4.0"
+            rating: string("xmp:Rating")
+                .and_then(|s| s.parse::<f64>().ok())
+                .map(|r| r.round() as i32),
+            label: string("xmp:Label"),
+        }
+    }
+
+    /// The IPTC-IIM records in the Photoshop resources of a JPEG's APP13 segment.
+    fn iim_from_jpeg<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+        let mut head = [0; 2];
+        reader.seek(SeekFrom::Start(0)).ok()?;
+        reader.read_exact(&mut head).ok()?;
+        if head != [0xff, 0xd8] {
+            return None;
+        }
+        let resources = xmp::jpeg_segment(reader, 0xffed, PHOTOSHOP_HEADER)?;
+        let mut r = ByteReader::new(&resources);
+        while r.remaining() >= 12 {
+            if &r.array4()? != b"8BIM" {
+                return None;
+            }
+            let id = r.u16()?;
+            // Pascal string name, padded to an even length including its length byte
+            let name_length = r.take(1)?[0] as usize;
+            r.take(name_length + (name_length + 1) % 2)?;
+            let size = r.u32()? as usize;
+            let data = r.take(size)?;
+            if id == IPTC_RESOURCE_ID {
+                return Some(data.to_vec());
+            }
+            r.skip(size % 2);
+        }
+        None
+    }
+
+    /// Parses IPTC-IIM application records (record 2). Text is UTF-8 in practice, and
+    /// Latin-1 in old files.
+    fn from_iim(iim: &[u8]) -> Self {
+        let mut ret = Self::default();
+        let mut r = ByteReader::new(iim);
+        while r.remaining() >= 5 {
+            let Some([0x1c, record, dataset]) = r.take(3).and_then(|b| <[u8; 3]>::try_from(b).ok())
+            else {
+                break;
+            };
+            // Lengths with the top bit set are extended lengths, only used for large
+            // binary data
+            let Some(data) = r
+                .u16()
+                .filter(|l| l & 0x8000 == 0)
+                .and_then(|l| r.take(l as usize))
+            else {
+                break;
+            };
+            if record != 2 {
+                continue;
+            }
+            let text = match std::str::from_utf8(data) {
+                Ok(s) => s.trim().to_string(),
+                Err(_) => data.iter().map(|b| *b as char).collect(),
+            };
+            if text.is_empty() {
+                continue;
+            }
+            match dataset {
+                5 => ret.title = Some(text),
+                25 => ret.keywords.push(text),
+                90 => ret.city = Some(text),
+                92 => ret.sublocation = Some(text),
+                95 => ret.state = Some(text),
+                101 => ret.country = Some(text),
+                120 => ret.caption = Some(text),
+                _ => {}
+            }
+        }
+        ret
+    }
+
+    /// The texts under their field names, and the keywords as one array, so the place
+    /// names can be shown and filtered on separately.
+    pub(crate) fn properties(&self) -> Vec<(&'static str, serde_json::Value)> {
+        let mut ret = Vec::new();
+        push_property(&mut ret, "title", self.title.as_ref());
+        push_property(&mut ret, "caption", self.caption.as_ref());
+        push_property(&mut ret, "sublocation", self.sublocation.as_ref());
+        push_property(&mut ret, "city", self.city.as_ref());
+        push_property(&mut ret, "state", self.state.as_ref());
+        push_property(&mut ret, "country", self.country.as_ref());
+        push_property(&mut ret, "label", self.label.as_ref());
+        push_property(
+            &mut ret,
+            "keywords",
+            (!self.keywords.is_empty()).then_some(&self.keywords),
+        );
+        push_property(&mut ret, "rating", self.rating);
+        ret
+    }
+
+    pub(crate) fn from_properties(properties: &Map<String, serde_json::Value>) -> Option<Self> {
+        Self {
+            title: property_string(properties, "title"),
+            caption: property_string(properties, "caption"),
+            keywords: property_strings(properties, "keywords"),
+            sublocation: property_string(properties, "sublocation"),
+            city: property_string(properties, "city"),
+            state: property_string(properties, "state"),
+            country: property_string(properties, "country"),
+            rating: property_string(properties, "rating").and_then(|s| s.parse().ok()),
+            label: property_string(properties, "label"),
+        }
+        .non_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn iim_record(dataset: u8, text: &[u8]) -> Vec<u8> {
+        let mut ret = vec![0x1c, 2, dataset];
+        ret.extend((text.len() as u16).to_be_bytes());
+        ret.extend(text);
+        ret
+    }
+
+    #[test]
+    fn test_from_jpeg_iim() {
+        let mut iim = vec![0x1c, 1, 90, 0, 3, 0x1b, b'%', b'G'];
+        iim.extend(iim_record(5, "Alba sulla laguna".as_bytes()));
+        iim.extend(iim_record(25, b"sunrise"));
+        iim.extend(iim_record(25, b"lagoon"));
+        iim.extend(iim_record(90, b"Venezia"));
+        iim.extend(iim_record(101, b"Italia"));
+        iim.extend(iim_record(120, b"Caf\xe9")); // Latin-1
+        let mut resources = b"Photoshop 3.0\08BIM\x04\x04\0\0".to_vec();
+        resources.extend((iim.len() as u32).to_be_bytes());
+        resources.extend(&iim);
+        let mut jpeg = vec![0xff, 0xd8, 0xff, 0xed];
+        jpeg.extend(((resources.len() + 2) as u16).to_be_bytes());
+        jpeg.extend(&resources);
+        jpeg.extend([0xff, 0xd9]);
+
+        let d = Description::from_image(&mut Cursor::new(&jpeg), None).unwrap();
+        assert_eq!(d.title, Some("Alba sulla laguna".to_string()));
+        assert_eq!(d.keywords, ["sunrise", "lagoon"]);
+        assert_eq!(d.city, Some("Venezia".to_string()));
+        assert_eq!(d.country, Some("Italia".to_string()));
+        assert_eq!(d.caption, Some("Café".to_string()));
+
+        // XMP wins, field by field
+        let xmp = Xmp::parse(
+            r#"<rdf:Description xmlns:photoshop="http://ns.adobe.com/photoshop/1.0/"
+                photoshop:City="Venice"/>"#,
+        )
+        .unwrap();
+        let d = Description::from_image(&mut Cursor::new(&jpeg), Some(&xmp)).unwrap();
+        assert_eq!(d.city, Some("Venice".to_string()));
+        assert_eq!(d.country, Some("Italia".to_string()));
+    }
+
+    #[test]
+    fn test_from_xmp() {
+        let xmp = Xmp::parse(
+            r#"<rdf:Description xmlns:dc="http://purl.org/dc/elements/1.1/"
+                xmlns:Iptc4xmpCore="http://iptc.org/std/Iptc4xmpCore/1.0/xmlns/"
+                xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+                Iptc4xmpCore:Location="Riva degli Schiavoni" xmp:Rating="4.0" xmp:Label="Red">
+                <dc:title><rdf:Alt><rdf:li xml:lang="x-default">Sunrise</rdf:li></rdf:Alt></dc:title>
+                <dc:subject><rdf:Bag><rdf:li>sunrise</rdf:li><rdf:li>lagoon</rdf:li></rdf:Bag></dc:subject>
+               </rdf:Description>"#,
+        )
+        .unwrap();
+        let d = Description::from_xmp(&xmp);
+        assert_eq!(d.title, Some("Sunrise".to_string()));
+        assert_eq!(d.keywords, ["sunrise", "lagoon"]);
+        assert_eq!(d.sublocation, Some("Riva degli Schiavoni".to_string()));
+        assert_eq!(d.caption, None);
+        assert_eq!(d.rating, Some(4));
+        assert_eq!(d.label, Some("Red".to_string()));
+        assert!(d.has_keyword("Lagoon"));
+    }
+
+    /// Keywords as written to KML, and a rejected (-1) rating as read back from text
+    #[test]
+    fn test_from_properties_as_text() {
+        let properties = serde_json::json!({
+            "keywords": r#"["sunrise","a, b"]"#,
+            "rating": "-1",
+        });
+        let d = Description::from_properties(properties.as_object().unwrap()).unwrap();
+        assert_eq!(d.keywords, ["sunrise", "a, b"]);
+        assert_eq!(d.rating, Some(-1));
+        // Not a JSON array: dropped rather than split
+        let properties = serde_json::json!({"keywords": "sunrise, lagoon"});
+        assert_eq!(
+            Description::from_properties(properties.as_object().unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_properties_no_empty_keywords() {
+        let d = Description {
+            city: Some("Venice".to_string()),
+            ..Default::default()
+        };
+        let keys: Vec<_> = d.properties().into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["city"]);
+    }
+}
@@ -0,0 +1,155 @@
+//! Flight logs that DJI drones write as `.SRT` subtitles next to each video. Every
+//! subtitle block describes one frame, with the drone's local date and time and its
+//! position, in one of several layouts depending on the model:
+//!
+//! - `[latitude: 45.505667] [longitude: 12.341161] [rel_alt: 30.100 abs_alt: 76.890]`
+//! - `[latitude : 45.505667] [longtitude : 12.341161] [altitude: 76.890]`
+//! - `GPS(12.341161,45.505667,76.890) BAROMETER:30.1`
+//!
+//! `rel_alt` and `BAROMETER` are the height above the take-off point.
+use crate::track::{Track, TrackPoint};
+use chrono::NaiveDateTime;
+use regex::Regex;
+use std::sync::LazyLock;
+
+static RE_DATE_TIME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(\d{4}[-.]\d{2}[-.]\d{2}) (\d{2}:\d{2}:\d{2}(?:[.,]\d+)?)")
+        .expect("re_date_time does not compile")
+});
+static RE_LATITUDE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[latitude\s*:\s*(-?[\d.]+)\]").expect("re_latitude does not compile")
+});
+// Some models misspell it "longtitude"
+static RE_LONGITUDE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[longt?itude\s*:\s*(-?[\d.]+)\]").expect("re_longitude does not compile")
+});
+static RE_ALTITUDE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:abs_alt|\[altitude)\s*:\s*(-?[\d.]+)").expect("re_altitude does not compile")
+});
+static RE_RELATIVE_ALTITUDE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:rel_alt|BAROMETER)\s*:\s*(-?[\d.]+)")
+        .expect("re_relative_altitude does not compile")
+});
+static RE_GPS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"GPS\s*\(\s*(-?[\d.]+)\s*,\s*(-?[\d.]+)\s*,\s*(-?[\d.]+)")
+        .expect("re_gps does not compile")
+});
+
+/// Parses a DJI SRT file into a track, with the local time of its first and last fix.
+/// Frames without a GPS fix (0, 0) are skipped. Returns `None` if there are no fixes,
+/// eg for an ordinary subtitle file.
+pub(crate) fn track_from_srt(text: &str) -> Option<Track> {
+    let mut track = Track::default();
+    for block in text.replace("\r\n", "\n").split("\n\n") {
+        let Some(mut point) = point_from_block(block) else {
+            continue;
+        };
+        if let Some(time) = time_from_block(block) {
+            let time = time.format("%Y-%m-%dT%H:%M:%S%.f").to_string();
+            track.start.get_or_insert_with(|| time.clone());
+            track.end = Some(time.clone());
+            point.time = Some(time);
+        }
+        track.points.push(point);
+    }
+    (!track.points.is_empty()).then_some(track)
+}
+
+fn point_from_block(block: &str) -> Option<TrackPoint> {
+    let number = |re: &Regex, group: usize| -> Option<f64> {
+        re.captures(block)?.get(group)?.as_str().parse().ok()
+    };
+    let (latitude, longitude, altitude) = match number(&RE_LATITUDE, 1) {
+        Some(latitude) => (latitude, number(&RE_LONGITUDE, 1)?, number(&RE_ALTITUDE, 1)),
+        None => (number(&RE_GPS, 2)?, number(&RE_GPS, 1)?, number(&RE_GPS, 3)),
+    };
+    if latitude == 0.0 && longitude == 0.0 {
+        return None;
+    }
+    Some(TrackPoint {
+        latitude,
+        longitude,
+        altitude,
+        relative_altitude: number(&RE_RELATIVE_ALTITUDE, 1),
+        time: None,
+    })
+}
+
+fn time_from_block(block: &str) -> Option<NaiveDateTime> {
+    let captures = RE_DATE_TIME.captures(block)?;
+    let date = captures.get(1)?.as_str().replace('.', "-");
+    let time = captures.get(2)?.as_str().replace(',', ".");
+    NaiveDateTime::parse_from_str(&format!("{date} {time}"), "%Y-%m-%d %H:%M:%S%.f").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAVIC3: &str = "1
+00:00:00,000 --> 00:00:00,033
+<font size=\"28\">FrameCnt: 1, DiffTime: 33ms
+2025-03-06 05:41:42.125
+[iso: 100] [shutter: 1/1000.0] [fnum: 2.8] [ev: 0] [focal_len: 24.00] [latitude: 45.505667] [longitude: 12.341161] [rel_alt: 30.100 abs_alt: 76.890] </font>
+
+2
+00:00:00,033 --> 00:00:00,066
+<font size=\"28\">FrameCnt: 2, DiffTime: 33ms
+2025-03-06 05:41:42.158
+[iso: 100] [shutter: 1/1000.0] [fnum: 2.8] [ev: 0] [focal_len: 24.00] [latitude: 45.505700] [longitude: 12.341200] [rel_alt: 30.200 abs_alt: 76.990] </font>
+";
+
+    #[test]
+    fn test_mavic3_layout() {
+        let track = track_from_srt(MAVIC3).unwrap();
+        assert_eq!(track.points.len(), 2);
+        assert_eq!(
+            track.points[0],
+            TrackPoint {
+                latitude: 45.505667,
+                longitude: 12.341161,
+                altitude: Some(76.89),
+                relative_altitude: Some(30.1),
+                time: Some("2025-03-06T05:41:42.125".to_string()),
+            }
+        );
+        assert_eq!(track.start, Some("2025-03-06T05:41:42.125".to_string()));
+        assert_eq!(track.end, Some("2025-03-06T05:41:42.158".to_string()));
+    }
+
+    #[test]
+    fn test_other_layouts() {
+        let mavic2 = "1\r\n00:00:00,000 --> 00:00:01,000\r\n2025.03.06 05:41:42\r\n\
+            [latitude : 45.505667] [longtitude : 12.341161] [altitude: 76.89]\r\n";
+        let phantom = "1\n00:00:00,000 --> 00:00:01,000\nHOME(12.3,45.5) 2025.03.06 05:41:42\n\
+            GPS(12.341161,45.505667,76.89) BAROMETER:30.1\n";
+        assert_eq!(
+            track_from_srt(mavic2).unwrap().points[0].relative_altitude,
+            None
+        );
+        assert_eq!(
+            track_from_srt(phantom).unwrap().points[0].relative_altitude,
+            Some(30.1)
+        );
+        for srt in [mavic2, phantom] {
+            let track = track_from_srt(srt).unwrap();
+            assert_eq!(track.points[0].latitude, 45.505667);
+            assert_eq!(track.points[0].longitude, 12.341161);
+            assert_eq!(track.points[0].altitude, Some(76.89));
+            assert_eq!(track.points[0].time, track.start);
+            assert_eq!(track.start, Some("2025-03-06T05:41:42".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_no_fix() {
+        let no_fix = MAVIC3
+            .replace("45.505667", "0.0")
+            .replace("12.341161", "0.0");
+        assert_eq!(track_from_srt(&no_fix).unwrap().points.len(), 1);
+        assert_eq!(
+            track_from_srt("1\n00:00:00,000 --> 00:00:01,000\nHello\n"),
+            None
+        );
+    }
+}
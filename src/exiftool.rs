@@ -0,0 +1,435 @@
+//! The JSON that `exiftool -j -n -GPS:all -DateTimeOriginal` writes: an array with one
+//! object per file, keyed by tag name (with a `Group:` prefix if `-G` was given). Each
+//! entry is turned into an EXIF block, like XMP, so it takes the same path as EXIF.
+//! Camera tags (`-EXIF:all`) and the IPTC/XMP description (`-XMP:all -IPTC:all`) are
+//! read as well if the JSON has them, for `--exif-fields` and the description filters.
+//!
+//! Values are numbers with `-n`; the printed forms exiftool uses without it (eg
+//! `45 deg 30' 20.40" N`) are understood for coordinates.
+use crate::description::Description;
+use crate::xmp::{ascii, field, rational};
+use exif::{Field, Rational, Tag, Value};
+use regex::Regex;
+use serde_json::Map;
+use std::io::Cursor;
+use std::sync::LazyLock;
+
+static RE_DMS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^(\d+(?:\.\d+)?) deg (\d+(?:\.\d+)?)' (\d+(?:\.\d+)?)"\s*([NSEW])?$"#)
+        .expect("re_dms does not compile")
+});
+/// DateTimeOriginal, with the sub-seconds and offset of the composite tags
+static RE_DATE_TIME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\d{4}:\d{2}:\d{2} \d{2}:\d{2}:\d{2})(?:\.(\d+))?([+-]\d{2}:\d{2})?")
+        .expect("re_date_time does not compile")
+});
+
+/// One file in exiftool's output.
+pub(crate) struct Entry<'a> {
+    tags: &'a Map<String, serde_json::Value>,
+}
+
+impl<'a> Entry<'a> {
+    pub(crate) fn new(v: &'a serde_json::Value) -> Option<Self> {
+        Some(Self {
+            tags: v.as_object()?,
+        })
+    }
+
+    pub(crate) fn source_file(&self) -> Option<&str> {
+        self.get("SourceFile")?.as_str()
+    }
+
+    /// A tag by name, ignoring any group prefix.
+    fn get(&self, name: &str) -> Option<&serde_json::Value> {
+        self.tags
+            .iter()
+            .find(|(key, _)| key.rsplit(':').next() == Some(name))
+            .map(|(_, value)| value)
+    }
+
+    fn string(&self, name: &str) -> Option<String> {
+        match self.get(name)? {
+            serde_json::Value::String(s) => Some(s.trim().to_string()),
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        }
+    }
+
+    fn number(&self, name: &str) -> Option<f64> {
+        let v = self.get(name)?;
+        v.as_f64()
+            .or_else(|| v.as_str()?.trim().parse().ok())
+            .filter(|f: &f64| f.is_finite())
+    }
+
+    /// A list tag such as `Keywords`, which exiftool writes as a plain value if there is
+    /// only one.
+    fn strings(&self, name: &str) -> Vec<String> {
+        match self.get(name) {
+            Some(serde_json::Value::Array(values)) => values
+                .iter()
+                .filter_map(|v| match v {
+                    serde_json::Value::String(s) => Some(s.trim().to_string()),
+                    serde_json::Value::Number(n) => Some(n.to_string()),
+                    _ => None,
+                })
+                .filter(|s| !s.is_empty())
+                .collect(),
+            Some(_) => self.string(name).into_iter().collect(),
+            None => vec![],
+        }
+    }
+
+    /// A coordinate and its hemisphere letter. With `-n` the `...Ref` tag holds the
+    /// letter; composite tags are signed instead.
+    fn coordinate(&self, name: &str, positive: char, negative: char) -> Option<(f64, char)> {
+        let reference = self
+            .string(&format!("{name}Ref"))
+            .and_then(|r| r.chars().next())
+            .map(|c| c.to_ascii_uppercase());
+        let (degrees, letter) = match self.number(name) {
+            Some(degrees) => (degrees, None),
+            None => {
+                let captures = RE_DMS.captures(self.get(name)?.as_str()?.trim())?;
+                let part = |i: usize| -> Option<f64> { captures.get(i)?.as_str().parse().ok() };
+                let degrees = part(1)? + part(2)? / 60.0 + part(3)? / 3600.0;
+                let letter = captures.get(4).and_then(|m| m.as_str().chars().next());
+                (degrees, letter)
+            }
+        };
+        let hemisphere = match reference.or(letter) {
+            Some(letter) => letter,
+            None if degrees < 0.0 => negative,
+            None => positive,
+        };
+        Some((degrees.abs(), hemisphere))
+    }
+
+    /// Converts the GPS and capture time tags into a TIFF-format EXIF block. Returns
+    /// `None` if there is no position.
+    pub(crate) fn to_exif(&self) -> Option<Vec<u8>> {
+        let mut fields = vec![];
+        for (name, tag, ref_tag, positive, negative) in [
+            (
+                "GPSLatitude",
+                Tag::GPSLatitude,
+                Tag::GPSLatitudeRef,
+                'N',
+                'S',
+            ),
+            (
+                "GPSLongitude",
+                Tag::GPSLongitude,
+                Tag::GPSLongitudeRef,
+                'E',
+                'W',
+            ),
+        ] {
+            let (degrees, hemisphere) = self.coordinate(name, positive, negative)?;
+            fields.push(field(tag, Value::Rational(vec![decimal(degrees)?])));
+            fields.push(field(ref_tag, ascii(&hemisphere.to_string())));
+        }
+        if let Some(altitude) = self.number("GPSAltitude")
+            && let Some(value) = decimal(altitude.abs())
+        {
+            // Printed as "Below Sea Level" without `-n`
+            let below_sea_level = altitude < 0.0
+                || self
+                    .string("GPSAltitudeRef")
+                    .is_some_and(|r| r == "1" || r.starts_with("Below"));
+            fields.push(field(Tag::GPSAltitude, Value::Rational(vec![value])));
+            fields.push(field(
+                Tag::GPSAltitudeRef,
+                Value::Byte(vec![below_sea_level as u8]),
+            ));
+        }
+        for (name, tag) in [
+            ("GPSImgDirection", Tag::GPSImgDirection),
+            ("GPSSpeed", Tag::GPSSpeed),
+            ("GPSTrack", Tag::GPSTrack),
+            ("GPSHPositioningError", Tag::GPSHPositioningError),
+            ("GPSDOP", Tag::GPSDOP),
+        ] {
+            if let Some(value) = self.number(name).and_then(decimal) {
+                fields.push(field(tag, Value::Rational(vec![value])));
+            }
+        }
+        for (name, tag) in [
+            ("GPSImgDirectionRef", Tag::GPSImgDirectionRef),
+            ("GPSMapDatum", Tag::GPSMapDatum),
+            ("GPSStatus", Tag::GPSStatus),
+            ("GPSMeasureMode", Tag::GPSMeasureMode),
+            ("GPSSatellites", Tag::GPSSatellites),
+            ("OffsetTimeOriginal", Tag::OffsetTimeOriginal),
+            ("SubSecTimeOriginal", Tag::SubSecTimeOriginal),
+        ] {
+            if let Some(value) = self.string(name) {
+                fields.push(field(tag, ascii(&value)));
+            }
+        }
+        if let Some(speed_ref) = self.string("GPSSpeedRef") {
+            // Printed as "km/h", "mph" or "knots" without `-n`
+            let unit = match speed_ref.as_str() {
+                "km/h" => "K",
+                "mph" => "M",
+                "knots" => "N",
+                letter => letter,
+            };
+            fields.push(field(Tag::GPSSpeedRef, ascii(unit)));
+        }
+        // "True North" or "Magnetic North" without `-n`
+        if let Some(letter) = self.string("GPSTrackRef").and_then(|r| r.chars().next()) {
+            let letter = letter.to_ascii_uppercase().to_string();
+            fields.push(field(Tag::GPSTrackRef, ascii(&letter)));
+        }
+        fields.extend(self.camera_fields());
+        if let Some(captured) = self.string("DateTimeOriginal") {
+            fields.extend(self.capture_time_fields(&captured));
+        }
+        fields.extend(self.gps_time_fields());
+
+        let mut writer = exif::experimental::Writer::new();
+        for f in &fields {
+            writer.push_field(f);
+        }
+        let mut buf = Cursor::new(Vec::new());
+        writer.write(&mut buf, false).ok()?;
+        Some(buf.into_inner())
+    }
+
+    /// Title, caption, keywords, place names, rating and label, XMP names first, then
+    /// IPTC-IIM ones, as `Description::from_image` prefers them.
+    pub(crate) fn description(&self) -> Option<Description> {
+        let first = |names: &[&str]| names.iter().find_map(|name| self.string(name));
+        let keywords = match self.strings("Subject") {
+            subject if subject.is_empty() => self.strings("Keywords"),
+            subject => subject,
+        };
+        Description {
+            title: first(&["Title", "ObjectName"]),
+            caption: first(&["Description", "Caption-Abstract"]),
+            keywords,
+            sublocation: first(&["Location", "Sub-location"]),
+            city: first(&["City"]),
+            state: first(&["State", "Province-State"]),
+            country: first(&["Country", "Country-PrimaryLocationName"]),
+            rating: self.number("Rating").map(|r| r.round() as i32),
+            label: first(&["Label"]),
+        }
+        .non_empty()
+    }
+
+    /// The tags `CameraInfo` reads, under exiftool's names for them.
+    fn camera_fields(&self) -> Vec<Field> {
+        let mut fields = vec![];
+        for (name, tag) in [
+            ("Make", Tag::Make),
+            ("Model", Tag::Model),
+            ("LensModel", Tag::LensModel),
+        ] {
+            if let Some(value) = self.string(name) {
+                fields.push(field(tag, ascii(&value)));
+            }
+        }
+        for (name, tag) in [
+            ("FocalLength", Tag::FocalLength),
+            ("ExposureTime", Tag::ExposureTime),
+            ("FNumber", Tag::FNumber),
+        ] {
+            if let Some(value) = self.number(name).and_then(decimal) {
+                fields.push(field(tag, Value::Rational(vec![value])));
+            }
+        }
+        let short = |name: &str| u16::try_from(self.number(name)?.round() as i64).ok();
+        for (name, tag) in [
+            ("FocalLengthIn35mmFormat", Tag::FocalLengthIn35mmFilm),
+            ("ISO", Tag::PhotographicSensitivity),
+        ] {
+            if let Some(value) = short(name) {
+                fields.push(field(tag, Value::Short(vec![value])));
+            }
+        }
+        let long = |name: &str| u32::try_from(self.number(name)?.round() as i64).ok();
+        for (names, tag) in [
+            (["ExifImageWidth", "ImageWidth"], Tag::PixelXDimension),
+            (["ExifImageHeight", "ImageHeight"], Tag::PixelYDimension),
+        ] {
+            if let Some(value) = names.iter().find_map(|name| long(name)) {
+                fields.push(field(tag, Value::Long(vec![value])));
+            }
+        }
+        fields
+    }
+
+    /// `DateTimeOriginal`, plus the sub-seconds and offset if they are part of it and
+    /// not tags of their own.
+    fn capture_time_fields(&self, s: &str) -> Vec<Field> {
+        let Some(captures) = RE_DATE_TIME.captures(s) else {
+            return vec![];
+        };
+        let mut ret = vec![field(Tag::DateTimeOriginal, ascii(&captures[1]))];
+        if let Some(subsec) = captures.get(2)
+            && self.get("SubSecTimeOriginal").is_none()
+        {
+            ret.push(field(Tag::SubSecTimeOriginal, ascii(subsec.as_str())));
+        }
+        if let Some(offset) = captures.get(3)
+            && self.get("OffsetTimeOriginal").is_none()
+        {
+            ret.push(field(Tag::OffsetTimeOriginal, ascii(offset.as_str())));
+        }
+        ret
+    }
+
+    /// `GPSDateStamp` and `GPSTimeStamp`, or the composite `GPSDateTime` holding both.
+    fn gps_time_fields(&self) -> Vec<Field> {
+        let date_time = self.string("GPSDateTime");
+        let (date, time) = match (self.string("GPSDateStamp"), self.string("GPSTimeStamp")) {
+            (Some(date), Some(time)) => (date, time),
+            _ => match date_time.as_deref().and_then(|dt| dt.split_once(' ')) {
+                Some((date, time)) => (date.to_string(), time.to_string()),
+                None => return vec![],
+            },
+        };
+        let time: Option<Vec<Rational>> = time
+            .trim_end_matches('Z')
+            .split(':')
+            .map(rational)
+            .collect();
+        match time {
+            Some(time) if time.len() == 3 => vec![
+                field(Tag::GPSDateStamp, ascii(&date)),
+                field(Tag::GPSTimeStamp, Value::Rational(time)),
+            ],
+            _ => vec![],
+        }
+    }
+}
+
+/// A non-negative number as a rational with up to six decimals, or fewer where the
+/// numerator would not fit in 32 bits, as for altitudes above 4295 m. `None` for
+/// negative numbers and numbers beyond `u32::MAX`, which a RATIONAL cannot hold.
+fn decimal(f: f64) -> Option<Rational> {
+    if !(0.0..=u32::MAX as f64).contains(&f) {
+        return None;
+    }
+    (0..=6).rev().find_map(|decimals| {
+        let denom = 10u32.pow(decimals);
+        let num = (f * denom as f64).round();
+        (num <= u32::MAX as f64).then(|| Rational::from((num as u32, denom)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::In;
+
+    fn exif(json: &str) -> exif::Exif {
+        let v: serde_json::Value = serde_json::from_str(json).unwrap();
+        let tiff = Entry::new(&v).unwrap().to_exif().unwrap();
+        exif::Reader::new().read_raw(tiff).unwrap()
+    }
+
+    fn get(exif: &exif::Exif, tag: Tag) -> String {
+        exif.get_field(tag, In::PRIMARY)
+            .unwrap()
+            .display_value()
+            .to_string()
+    }
+
+    #[test]
+    fn test_numeric_entry() {
+        let exif = exif(
+            r#"{"SourceFile": "a.cr2", "GPSLatitude": 45.505667, "GPSLatitudeRef": "N",
+                "GPSLongitude": 12.341161, "GPSLongitudeRef": "W", "GPSAltitude": 46.79,
+                "GPSAltitudeRef": 1, "GPSImgDirection": 11, "GPSImgDirectionRef": "M",
+                "GPSDateStamp": "2025:03:06", "GPSTimeStamp": "05:41:36.5",
+                "DateTimeOriginal": "2025:03:06 05:41:42"}"#,
+        );
+        assert_eq!(get(&exif, Tag::GPSLatitude), "45505667/1000000");
+        assert_eq!(get(&exif, Tag::GPSLongitudeRef), "W");
+        assert_eq!(get(&exif, Tag::GPSAltitude), "46.79");
+        assert_eq!(get(&exif, Tag::GPSAltitudeRef), "below sea level");
+        assert_eq!(get(&exif, Tag::GPSImgDirection), "11");
+        assert_eq!(get(&exif, Tag::GPSDateStamp), "2025-03-06");
+        assert_eq!(get(&exif, Tag::GPSTimeStamp), "05:41:36.5");
+        assert_eq!(get(&exif, Tag::DateTimeOriginal), "2025-03-06 05:41:42");
+    }
+
+    /// Six decimals of 4295 or more overflow a 32-bit numerator; such values must lose
+    /// precision, not the tag or the whole entry.
+    #[test]
+    fn test_large_values() {
+        let exif = exif(
+            r#"{"SourceFile": "a.jpg", "GPSLatitude": 27.988056, "GPSLatitudeRef": "N",
+                "GPSLongitude": 86.925278, "GPSLongitudeRef": "E", "GPSAltitude": 5000.25,
+                "GPSHPositioningError": 5000}"#,
+        );
+        assert_eq!(get(&exif, Tag::GPSAltitude), "5000.25");
+        assert_eq!(get(&exif, Tag::GPSAltitudeRef), "above sea level");
+        assert_eq!(get(&exif, Tag::GPSHPositioningError), "5000");
+        assert_eq!(
+            decimal(5000.123456).map(|r| (r.num, r.denom)),
+            Some((500012346, 100000))
+        );
+        assert!(decimal(-1.0).is_none());
+        assert!(decimal(5e9).is_none());
+    }
+
+    #[test]
+    fn test_composite_and_printed_entry() {
+        let exif = exif(
+            r#"{"SourceFile": "a.cr2", "Composite:GPSLatitude": -45.505667,
+                "Composite:GPSLongitude": "12 deg 20' 28.18\" E",
+                "Composite:GPSDateTime": "2025:03:06 05:41:36Z",
+                "EXIF:DateTimeOriginal": "2025:03:06 05:41:42.125+01:00",
+                "GPSSpeed": 12.5, "GPSSpeedRef": "knots", "GPSTrackRef": "True North"}"#,
+        );
+        assert_eq!(get(&exif, Tag::GPSLatitudeRef), "S");
+        assert_eq!(get(&exif, Tag::GPSLongitudeRef), "E");
+        assert_eq!(get(&exif, Tag::GPSTimeStamp), "05:41:36");
+        assert_eq!(get(&exif, Tag::SubSecTimeOriginal), "\"125\"");
+        assert_eq!(get(&exif, Tag::OffsetTimeOriginal), "\"+01:00\"");
+        assert_eq!(get(&exif, Tag::GPSSpeed), "12.5");
+        assert_eq!(get(&exif, Tag::GPSSpeedRef), "knots");
+        assert_eq!(get(&exif, Tag::GPSTrackRef), "true direction");
+
+        let v = serde_json::json!({"SourceFile": "a.cr2", "DateTimeOriginal": "2025:03:06"});
+        assert_eq!(Entry::new(&v).unwrap().to_exif(), None);
+    }
+
+    #[test]
+    fn test_camera_fields() {
+        let exif = exif(
+            r#"{"SourceFile": "a.cr2", "GPSLatitude": 45.5, "GPSLongitude": 12.3,
+                "Make": "Canon", "Model": "Canon EOS R5", "FocalLength": 24,
+                "ExposureTime": 0.004, "FNumber": 2.8, "ISO": 400,
+                "FocalLengthIn35mmFormat": 24, "ExifImageWidth": 8192, "ImageHeight": 5464}"#,
+        );
+        assert_eq!(get(&exif, Tag::Model), "\"Canon EOS R5\"");
+        assert_eq!(get(&exif, Tag::ExposureTime), "1/250");
+        assert_eq!(get(&exif, Tag::PhotographicSensitivity), "400");
+        assert_eq!(get(&exif, Tag::PixelXDimension), "8192");
+        assert_eq!(get(&exif, Tag::PixelYDimension), "5464");
+    }
+
+    #[test]
+    fn test_description() {
+        let v = serde_json::json!({"SourceFile": "a.jpg", "XMP:Subject": ["sunrise", "Venice"],
+            "IPTC:Keywords": "lagoon", "Rating": 4, "Label": "Red", "City": "Venice",
+            "ObjectName": "Sunrise"});
+        let description = Entry::new(&v).unwrap().description().unwrap();
+        assert_eq!(description.keywords, ["sunrise", "Venice"]);
+        assert_eq!(description.rating, Some(4));
+        assert_eq!(description.label.as_deref(), Some("Red"));
+        assert_eq!(description.title.as_deref(), Some("Sunrise"));
+        // A single keyword is not an array
+        let v = serde_json::json!({"SourceFile": "a.jpg", "Keywords": "lagoon"});
+        let description = Entry::new(&v).unwrap().description().unwrap();
+        assert_eq!(description.keywords, ["lagoon"]);
+        let v = serde_json::json!({"SourceFile": "a.jpg", "GPSLatitude": 45.5});
+        assert_eq!(Entry::new(&v).unwrap().description(), None);
+    }
+}
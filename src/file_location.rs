@@ -1,51 +1,844 @@
+use crate::camera_info::CameraInfo;
+use crate::date_time::{self, EXIF_FORMAT};
+use crate::datum::Datum;
+use crate::description::Description;
+use crate::dji_srt;
+use crate::exiftool;
+use crate::file_type::OpenedFile;
+use crate::gpmf;
+use crate::gps_accuracy::GpsAccuracy;
+use crate::heif;
+use crate::lightroom::CatalogImage;
+use crate::object_location::ObjectLocation;
+use crate::quicktime;
+use crate::raw;
+use crate::takeout::{self, TakeoutMetadata};
+use crate::track::Track;
+use crate::validation::Validity;
+use crate::xmp::{self, CameraPose};
 use base64::{engine::general_purpose, Engine};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, SecondsFormat, TimeDelta, Utc};
 use exif::{Exif, In, Tag, Value};
 use geojson::GeoJson;
 use kml::Kml;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{Map, json};
 use std::{
     fs::File,
-    io::{BufReader, Cursor},
+    io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
 };
 use thumbnailer::{create_thumbnails, ThumbnailSize};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// EXIF tags that are read when present but are not required to place an image on a
+/// map. Absent ones are listed in `FileLocation::missing_tags`.
+const OPTIONAL_TAGS: &[Tag] = &[
+    Tag::GPSAltitude,
+    Tag::GPSImgDirection,
+    Tag::DateTimeOriginal,
+];
+
+/// Reference for `FileLocation::direction`, from EXIF `GPSImgDirectionRef`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DirectionRef {
+    #[serde(rename = "T")]
+    True,
+    #[serde(rename = "M")]
+    Magnetic,
+}
+
+impl DirectionRef {
+    pub(crate) fn from_letter(letter: char) -> Option<Self> {
+        match letter.to_ascii_uppercase() {
+            'T' => Some(Self::True),
+            'M' => Some(Self::Magnetic),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::True => "T",
+            Self::Magnetic => "M",
+        }
+    }
+}
+
+/// Where `FileLocation`'s capture time was read from, if not from the one capture time
+/// a video, Takeout sidecar or catalog has.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampSource {
+    /// EXIF `DateTimeOriginal`
+    Original,
+    /// EXIF `DateTimeDigitized`, for images without `DateTimeOriginal`
+    Digitized,
+    /// EXIF `DateTime`, which editors update when saving
+    Modified,
+    /// Only the GPS fix time, in `timestamp_utc`
+    Gps,
+    /// A date and time in the file name, eg `IMG_20250306_054142.jpg`
+    FileName,
+    /// The file's modification time, in `timestamp_utc`
+    FileTime,
+}
+
+impl TimestampSource {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "original" => Some(Self::Original),
+            "digitized" => Some(Self::Digitized),
+            "modified" => Some(Self::Modified),
+            "gps" => Some(Self::Gps),
+            "file_name" => Some(Self::FileName),
+            "file_time" => Some(Self::FileTime),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Original => "original",
+            Self::Digitized => "digitized",
+            Self::Modified => "modified",
+            Self::Gps => "gps",
+            Self::FileName => "file_name",
+            Self::FileTime => "file_time",
+        }
+    }
+}
+
+/// EXIF date/time tags with their offset and sub-second tags, in order of preference.
+const CAPTURE_TIME_TAGS: &[(Tag, Tag, Tag, TimestampSource)] = &[
+    (
+        Tag::DateTimeOriginal,
+        Tag::OffsetTimeOriginal,
+        Tag::SubSecTimeOriginal,
+        TimestampSource::Original,
+    ),
+    (
+        Tag::DateTimeDigitized,
+        Tag::OffsetTimeDigitized,
+        Tag::SubSecTimeDigitized,
+        TimestampSource::Digitized,
+    ),
+    (
+        Tag::DateTime,
+        Tag::OffsetTime,
+        Tag::SubSecTime,
+        TimestampSource::Modified,
+    ),
+];
+
+/// Where `FileLocation::timestamp_utc` was derived from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UtcSource {
+    /// `DateTimeOriginal` shifted by `OffsetTimeOriginal`
+    Offset,
+    /// `GPSDateStamp` + `GPSTimeStamp`, which are UTC by definition
+    Gps,
+    /// The creation time in a video's movie header, also UTC
+    Container,
+    /// `photoTakenTime` of a Google Takeout sidecar, also UTC
+    Takeout,
+    /// The file's modification time, the last resort
+    FileTime,
+}
+
+impl UtcSource {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "offset" => Some(Self::Offset),
+            "gps" => Some(Self::Gps),
+            "container" => Some(Self::Container),
+            "takeout" => Some(Self::Takeout),
+            "file_time" => Some(Self::FileTime),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Offset => "offset",
+            Self::Gps => "gps",
+            Self::Container => "container",
+            Self::Takeout => "takeout",
+            Self::FileTime => "file_time",
+        }
+    }
+}
+
+/// Where `FileLocation`'s position was read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LocationSource {
+    /// The EXIF block of the image
+    Exif,
+    /// The XMP packet embedded in the image
+    Xmp,
+    /// An XMP sidecar file next to the image
+    XmpSidecar,
+    /// QuickTime/MP4 video metadata
+    QuickTime,
+    /// The first fix of a GoPro GPMF telemetry track
+    Gpmf,
+    /// The first fix of a DJI drone SRT flight log
+    DjiSrt,
+    /// A Google Photos Takeout JSON sidecar
+    Takeout,
+    /// exiftool's JSON output, see `FileSet::import_exiftool_json`
+    Exiftool,
+    /// A Lightroom Classic catalog, see `FileSet::load_lightroom_catalog`
+    Lightroom,
+}
+
+impl LocationSource {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "exif" => Some(Self::Exif),
+            "xmp" => Some(Self::Xmp),
+            "xmp_sidecar" => Some(Self::XmpSidecar),
+            "quicktime" => Some(Self::QuickTime),
+            "gpmf" => Some(Self::Gpmf),
+            "dji_srt" => Some(Self::DjiSrt),
+            "takeout" => Some(Self::Takeout),
+            "exiftool" => Some(Self::Exiftool),
+            "lightroom" => Some(Self::Lightroom),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Exif => "exif",
+            Self::Xmp => "xmp",
+            Self::XmpSidecar => "xmp_sidecar",
+            Self::QuickTime => "quicktime",
+            Self::Gpmf => "gpmf",
+            Self::DjiSrt => "dji_srt",
+            Self::Takeout => "takeout",
+            Self::Exiftool => "exiftool",
+            Self::Lightroom => "lightroom",
+        }
+    }
+}
+
+/// Whether a `FileLocation` is a photo or a video.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    #[default]
+    Image,
+    Video,
+}
+
+impl MediaType {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "image" => Some(Self::Image),
+            "video" => Some(Self::Video),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Image => "image",
+            Self::Video => "video",
+        }
+    }
+}
+
+/// How XMP sidecar files (`photo.xmp` or `photo.jpg.xmp`) are used. Google Takeout
+/// sidecars are only ever a fallback, and are not read with `Ignore` either.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SidecarMode {
+    /// Only for images without a position of their own
+    #[default]
+    Fallback,
+    /// The sidecar position replaces the one in the image
+    Prefer,
+    /// Sidecars are not read
+    Ignore,
+}
+
+/// Controls what is read from an image beyond its position.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReadOptions {
+    /// Read camera, lens and exposure metadata into `FileLocation::camera`
+    pub exif_fields: bool,
+    pub sidecars: SidecarMode,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct FileLocation {
     pub file: String,
     pub latitude: f64,
     pub longitude: f64,
-    pub altitude: Option<f64>,  // ATTENTION sea level implied, not checked
-    pub direction: Option<f64>, // ATTENTION magnetic direction implied, not checked
+    pub altitude: Option<f64>, // Metres, negative below sea level
+    pub direction: Option<f64>,
+    #[serde(default)]
+    pub direction_ref: Option<DirectionRef>, // None if the image did not say
     pub thumbnail: Option<String>,
-    pub timestamp: Option<String>,
+    pub timestamp: Option<String>, // Local wall-clock time, as written by the camera
+    #[serde(default)]
+    pub timestamp_offset: Option<String>, // UTC offset of `timestamp`, eg "+01:00"
+    #[serde(default)]
+    pub timestamp_utc: Option<String>, // RFC 3339, including sub-seconds if known
+    #[serde(default)]
+    pub timestamp_utc_source: Option<UtcSource>,
+    #[serde(default)]
+    pub timestamp_gps: Option<String>, // RFC 3339, time of the GPS fix
+    /// Optional EXIF tags (see `OPTIONAL_TAGS`) the image did not carry, so partial GPS
+    /// data can be told apart from no GPS data at all.
+    #[serde(default)]
+    pub missing_tags: Vec<String>,
+    #[serde(default)]
+    pub camera: Option<CameraInfo>,
+    #[serde(default)]
+    pub accuracy: Option<GpsAccuracy>,
+    /// Set instead of dropping the location when it is less accurate than requested.
+    #[serde(default)]
+    pub low_accuracy: bool,
+    #[serde(default)]
+    pub validity: Validity,
+    #[serde(default)]
+    pub location_source: Option<LocationSource>,
+    #[serde(default)]
+    pub media_type: MediaType,
+    /// GPS track of a video, written as a separate LineString next to the Point
+    #[serde(default)]
+    pub track: Option<Track>,
+    #[serde(default)]
+    pub pitch: Option<f64>, // Camera pitch in degrees, negative looking down
+    #[serde(default)]
+    pub relative_altitude: Option<f64>, // Metres above the take-off point (drones)
+    /// Title, caption, keywords and place names from IPTC/XMP
+    #[serde(default)]
+    pub description: Option<Description>,
+    /// Detected from the file's content, not its extension
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    #[serde(default)]
+    pub timestamp_source: Option<TimestampSource>,
+    /// Position of the photographed object, from the `GPSDest*` tags
+    #[serde(default)]
+    pub object: Option<ObjectLocation>,
+    #[serde(default)]
+    pub speed: Option<f64>, // Metres per second, from `GPSSpeed`
+    /// Direction of travel (`GPSTrack`), which may differ from the camera `direction`
+    #[serde(default)]
+    pub travel_direction: Option<f64>,
+    #[serde(default)]
+    pub travel_direction_ref: Option<DirectionRef>,
+    /// `GPSMapDatum`, if not WGS 84. The coordinates have been converted from it to
+    /// WGS 84, unless it is unknown; `validate` flags those.
+    #[serde(default)]
+    pub map_datum: Option<String>,
 }
 
 impl FileLocation {
     pub fn from_file(file_name: &str) -> Option<Self> {
-        let file = std::fs::File::open(file_name).ok()?;
-        let mut bufreader = std::io::BufReader::new(&file);
+        Self::from_file_with_options(file_name, &ReadOptions::default())
+    }
+
+    pub fn from_file_with_options(file_name: &str, options: &ReadOptions) -> Option<Self> {
+        Self::from_opened_file(file_name, &mut OpenedFile::open(file_name)?, options)
+    }
+
+    /// For callers that opened the file and sniffed its type already, see
+    /// `FileSet::add_files`. All embedded metadata is read through this one reader.
+    pub(crate) fn from_opened_file(
+        file_name: &str,
+        file: &mut OpenedFile,
+        options: &ReadOptions,
+    ) -> Option<Self> {
+        let fl = Self::from_file_and_sidecars(file_name, &mut file.reader, options)?;
+        Some(Self {
+            mime_type: file.file_type.map(|t| t.mime_type().to_string()),
+            ..fl.with_fallback_time(file_name)
+        })
+    }
+
+    /// For files without any capture time, the date and time in the file name, or else
+    /// the file's modification time, so date filters do not drop them.
+    fn with_fallback_time(self, file_name: &str) -> Self {
+        if self.timestamp.is_some() || self.timestamp_utc.is_some() {
+            return self;
+        }
+        if let Some(local) = date_time::from_file_name(file_name) {
+            return Self {
+                timestamp: Some(local.format(EXIF_FORMAT).to_string()),
+                timestamp_source: Some(TimestampSource::FileName),
+                ..self
+            };
+        }
+        let Some(modified) = std::fs::metadata(file_name)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(DateTime::<Utc>::from)
+        else {
+            return self;
+        };
+        Self {
+            timestamp_utc: Some(modified.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+            timestamp_utc_source: Some(UtcSource::FileTime),
+            timestamp_source: Some(TimestampSource::FileTime),
+            ..self
+        }
+    }
+
+    fn from_file_and_sidecars<R: BufRead + Seek>(
+        file_name: &str,
+        reader: &mut R,
+        options: &ReadOptions,
+    ) -> Option<Self> {
+        let embedded = Self::from_embedded(file_name, reader, options);
+        let sidecar = || Self::from_sidecar(file_name, options);
+        let fl = match options.sidecars {
+            SidecarMode::Ignore => return embedded,
+            SidecarMode::Fallback => embedded.or_else(sidecar),
+            SidecarMode::Prefer => match (embedded, sidecar()) {
+                (Some(embedded), Some(sidecar)) => Some(embedded.with_position_of(sidecar)),
+                (embedded, sidecar) => sidecar.or(embedded),
+            },
+        };
+        let fl = fl.map(|fl| fl.with_sidecar_description(file_name));
+        match fl {
+            Some(fl) if fl.timestamp.is_some() || fl.timestamp_utc.is_some() => Some(fl),
+            fl => Self::with_takeout(file_name, fl),
+        }
+    }
+
+    /// Fills in the position and capture time from a Google Takeout sidecar where the
+    /// image had none.
+    fn with_takeout(file_name: &str, fl: Option<Self>) -> Option<Self> {
+        let Some(takeout) = takeout::sidecar_path(file_name)
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|json| TakeoutMetadata::parse(&json))
+        else {
+            return fl;
+        };
+        let mut fl = match fl {
+            Some(fl) => fl,
+            None => Self {
+                file: file_name.to_string(),
+                latitude: takeout.latitude?,
+                longitude: takeout.longitude?,
+                altitude: takeout.altitude,
+                location_source: Some(LocationSource::Takeout),
+                ..Default::default()
+            },
+        };
+        if fl.timestamp.is_none()
+            && fl.timestamp_utc.is_none()
+            && let Some(taken) = takeout.taken
+        {
+            fl.timestamp_utc = Some(taken.to_rfc3339_opts(SecondsFormat::AutoSi, true));
+            fl.timestamp_utc_source = Some(UtcSource::Takeout);
+        }
+        Some(fl)
+    }
+
+    /// The location stored in the image itself, from EXIF or else from embedded XMP.
+    /// Drone and panorama camera orientation and the description are taken from the
+    /// XMP (and IPTC-IIM) either way.
+    fn from_embedded<R: BufRead + Seek>(
+        file_name: &str,
+        reader: &mut R,
+        options: &ReadOptions,
+    ) -> Option<Self> {
+        if Self::has_extension(file_name, "srt") {
+            return Self::from_dji_srt(file_name, reader);
+        }
+        let xmp = xmp::xmp_from_image(reader);
+        let fl = Self::read_exif(reader)
+            .and_then(|exif| Self::from_exif(file_name, &exif, options))
+            .map(|fl| fl.with_source(LocationSource::Exif))
+            .or_else(|| {
+                let exif = exif::Reader::new()
+                    .read_raw(xmp.as_ref()?.to_exif()?)
+                    .ok()?;
+                Self::from_exif(file_name, &exif, options)
+                    .map(|fl| fl.with_source(LocationSource::Xmp))
+            });
+        let description = Description::from_image(reader, xmp.as_ref());
+        let fl = match (fl, xmp) {
+            (Some(fl), Some(xmp)) => fl.with_camera_pose(xmp.camera_pose()),
+            (Some(fl), None) => fl,
+            (None, _) => return Self::from_video(file_name, reader),
+        };
+        Some(Self { description, ..fl })
+    }
+
+    /// The location of a QuickTime/MP4 video, with its creation time. The local time is
+    /// only known if the metadata keys have it; the movie header time is UTC. Videos
+    /// with a GPS track (GoPro) are placed at its first fix unless they say otherwise.
+    fn from_video<R: Read + Seek>(file_name: &str, reader: &mut R) -> Option<Self> {
+        let track = gpmf::track_from_video(reader);
+        let Some(video) = quicktime::metadata_from_video(reader) else {
+            return Self::from_track(file_name, track?);
+        };
+        let (timestamp, timestamp_offset, utc) = match (video.creation_date, video.creation_time) {
+            (Some(local), _) => (
+                Some(local.format("%Y:%m:%d %H:%M:%S").to_string()),
+                Some(local.format("%:z").to_string()),
+                Some((local.with_timezone(&Utc), UtcSource::Offset)),
+            ),
+            (None, Some(utc)) => (None, None, Some((utc, UtcSource::Container))),
+            (None, None) => (None, None, None),
+        };
+        Some(Self {
+            file: file_name.to_string(),
+            latitude: video.latitude,
+            longitude: video.longitude,
+            altitude: video.altitude,
+            timestamp,
+            timestamp_offset,
+            timestamp_utc: utc.map(|(dt, _)| dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+            timestamp_utc_source: utc.map(|(_, source)| source),
+            location_source: Some(LocationSource::QuickTime),
+            media_type: MediaType::Video,
+            track,
+            ..Default::default()
+        })
+    }
+
+    fn from_track(file_name: &str, track: Track) -> Option<Self> {
+        let first = track.points.first()?;
+        Some(Self {
+            file: file_name.to_string(),
+            latitude: first.latitude,
+            longitude: first.longitude,
+            altitude: first.altitude,
+            timestamp_utc: track.start.clone(),
+            timestamp_utc_source: track.start.as_ref().map(|_| UtcSource::Gps),
+            timestamp_gps: track.start.clone(),
+            location_source: Some(LocationSource::Gpmf),
+            media_type: MediaType::Video,
+            track: Some(track),
+            ..Default::default()
+        })
+    }
+
+    /// The flight log a DJI drone writes next to each video, placed at its first fix. Its
+    /// times are the drone's local time, with no UTC offset.
+    fn from_dji_srt<R: Read + Seek>(file_name: &str, reader: &mut R) -> Option<Self> {
+        let mut text = String::new();
+        reader.seek(SeekFrom::Start(0)).ok()?;
+        reader.read_to_string(&mut text).ok()?;
+        let track = dji_srt::track_from_srt(&text)?;
+        let first = track.points.first()?;
+        let timestamp = track
+            .start
+            .as_deref()
+            .and_then(|start| NaiveDateTime::parse_from_str(start, "%Y-%m-%dT%H:%M:%S%.f").ok())
+            .map(|start| start.format("%Y:%m:%d %H:%M:%S").to_string());
+        Some(Self {
+            file: file_name.to_string(),
+            latitude: first.latitude,
+            longitude: first.longitude,
+            altitude: first.altitude,
+            relative_altitude: first.relative_altitude,
+            timestamp,
+            location_source: Some(LocationSource::DjiSrt),
+            media_type: MediaType::Video,
+            track: Some(track),
+            ..Default::default()
+        })
+    }
+
+    fn has_extension(file_name: &str, extension: &str) -> bool {
+        Path::new(file_name)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+    }
+
+    /// The location from an XMP sidecar of the image, if there is one.
+    fn from_sidecar(file_name: &str, options: &ReadOptions) -> Option<Self> {
+        let xmp = Self::read_sidecar_xmp(file_name)?;
+        let exif = exif::Reader::new().read_raw(xmp.to_exif()?).ok()?;
+        Self::from_exif(file_name, &exif, options).map(|fl| {
+            fl.with_source(LocationSource::XmpSidecar)
+                .with_camera_pose(xmp.camera_pose())
+        })
+    }
+
+    fn read_sidecar_xmp(file_name: &str) -> Option<xmp::Xmp> {
+        let packet = std::fs::read_to_string(Self::sidecar_path(file_name)?).ok()?;
+        xmp::Xmp::parse(&packet)
+    }
+
+    /// Editors such as Lightroom keep ratings, labels and keywords in the sidecar, so its
+    /// description wins over the image's, field by field, whichever position is used.
+    fn with_sidecar_description(self, file_name: &str) -> Self {
+        let Some(xmp) = Self::read_sidecar_xmp(file_name) else {
+            return self;
+        };
+        let embedded = self.description.clone().unwrap_or_default();
+        Self {
+            description: Description::from_xmp(&xmp).or(embedded).non_empty(),
+            ..self
+        }
+    }
+
+    /// The location in one entry of exiftool's JSON output, for files exiftool can read
+    /// but this crate cannot. The file itself is not opened.
+    pub fn from_exiftool_entry(v: &serde_json::Value, options: &ReadOptions) -> Option<Self> {
+        let entry = exiftool::Entry::new(v)?;
+        let exif = exif::Reader::new().read_raw(entry.to_exif()?).ok()?;
+        let fl = Self::from_exif(entry.source_file()?, &exif, options)?;
+        Some(Self {
+            description: entry.description(),
+            ..fl.with_source(LocationSource::Exiftool)
+        })
+    }
+
+    /// The location of an image in a Lightroom catalog. The file itself is not opened.
+    pub(crate) fn from_lightroom(image: CatalogImage) -> Self {
+        let utc = image
+            .capture_time
+            .zip(image.offset)
+            .and_then(|(local, offset)| local.and_local_timezone(offset).single());
+        Self {
+            file: image.file,
+            latitude: image.latitude,
+            longitude: image.longitude,
+            timestamp: image
+                .capture_time
+                .map(|t| t.format("%Y:%m:%d %H:%M:%S").to_string()),
+            timestamp_offset: image.offset.map(|o| o.to_string()),
+            timestamp_utc: utc.map(|dt| {
+                dt.with_timezone(&Utc)
+                    .to_rfc3339_opts(SecondsFormat::AutoSi, true)
+            }),
+            timestamp_utc_source: utc.map(|_| UtcSource::Offset),
+            location_source: Some(LocationSource::Lightroom),
+            ..Default::default()
+        }
+    }
+
+    /// Sidecars are named either after the whole image file name or after its stem,
+    /// depending on the application that wrote them.
+    fn sidecar_path(file_name: &str) -> Option<PathBuf> {
+        let path = Path::new(file_name);
+        ["xmp", "XMP"]
+            .iter()
+            .flat_map(|ext| {
+                [
+                    PathBuf::from(format!("{file_name}.{ext}")),
+                    path.with_extension(ext),
+                ]
+            })
+            .find(|p| p.is_file())
+    }
+
+    fn with_source(self, source: LocationSource) -> Self {
+        Self {
+            location_source: Some(source),
+            ..self
+        }
+    }
+
+    /// Fills in what the EXIF block did not have from the XMP camera pose.
+    fn with_camera_pose(self, pose: CameraPose) -> Self {
+        Self {
+            direction: self.direction.or(pose.direction),
+            pitch: self.pitch.or(pose.pitch),
+            relative_altitude: self.relative_altitude.or(pose.relative_altitude),
+            ..self
+        }
+    }
+
+    /// Takes the position from `other`, keeping the rest (timestamps, camera) of `self`.
+    fn with_position_of(self, other: Self) -> Self {
+        Self {
+            latitude: other.latitude,
+            longitude: other.longitude,
+            altitude: other.altitude.or(self.altitude),
+            direction: other.direction.or(self.direction),
+            direction_ref: other
+                .direction
+                .and(other.direction_ref)
+                .or(self.direction_ref),
+            pitch: other.pitch.or(self.pitch),
+            relative_altitude: other.relative_altitude.or(self.relative_altitude),
+            object: other.object.or(self.object),
+            speed: other.speed.or(self.speed),
+            travel_direction: other.travel_direction.or(self.travel_direction),
+            travel_direction_ref: other
+                .travel_direction
+                .and(other.travel_direction_ref)
+                .or(self.travel_direction_ref),
+            map_datum: other.map_datum,
+            accuracy: other.accuracy,
+            location_source: other.location_source,
+            ..self
+        }
+    }
+
+    /// Reads the EXIF block of a file, trying our own container parsers for the formats
+    /// the EXIF crate does not handle (fully).
+    fn read_exif<R: BufRead + Seek>(reader: &mut R) -> Option<Exif> {
         let exifreader = exif::Reader::new();
-        let exif = exifreader.read_from_container(&mut bufreader).ok()?;
-        Self::from_exif(file_name, &exif)
+        reader.seek(SeekFrom::Start(0)).ok()?;
+        if let Ok(exif) = exifreader.read_from_container(reader) {
+            return Some(exif);
+        }
+        let tiff = heif::exif_from_heif(reader).or_else(|| raw::exif_from_raw(reader))?;
+        exifreader.read_raw(tiff).ok()
     }
 
+    /// The placemark is named after the image title if it has one, with the file name
+    /// in `<ExtendedData>` then.
     pub fn as_kml(&self) -> String {
         let mut other = String::new();
-        if let Some(timestamp) = &self.timestamp {
+        if let Some(timestamp) = self.timestamp_utc.as_ref().or(self.timestamp.as_ref()) {
             other += &format!(
                 "<TimeStamp><when>{}</when></TimeStamp>",
                 timestamp.replace(' ', "T")
             );
         }
+        let data = self.kml_extended_data();
+        if !data.is_empty() {
+            other += "<ExtendedData>";
+            for (name, value) in data {
+                // Numbers as Rust prints them, so 11.0 is "11" as it always was
+                let value = match value {
+                    serde_json::Value::String(s) => s,
+                    serde_json::Value::Number(n) if n.is_f64() => {
+                        n.as_f64().unwrap_or_default().to_string()
+                    }
+                    other => other.to_string(),
+                };
+                other += &format!(
+                    "<Data name=\"{name}\"><value>{}</value></Data>",
+                    xml_escaped(&value)
+                );
+            }
+            other += "</ExtendedData>";
+        }
         format!("<Placemark><name>{}</name><Point><coordinates>{},{},{}</coordinates></Point>{other}</Placemark>",
-            self.name_xml_escaped(),
+            xml_escaped(self.kml_name()),
             self.longitude,
             self.latitude,
             self.altitude.unwrap_or(0.0),
         )
     }
 
+    fn kml_name(&self) -> &str {
+        self.description
+            .as_ref()
+            .and_then(|d| d.title.as_deref())
+            .unwrap_or(&self.file)
+    }
+
+    /// Properties that KML has no dedicated element for, written as `<ExtendedData>`.
+    fn kml_extended_data(&self) -> Vec<(&'static str, serde_json::Value)> {
+        let mut data = Vec::new();
+        if self.kml_name() != self.file {
+            data.push(("file", json!(self.file)));
+        }
+        if let Some(direction) = self.direction {
+            data.push(("direction", json!(direction)));
+        }
+        if let Some(direction_ref) = self.direction_ref {
+            data.push(("direction_ref", json!(direction_ref.as_str())));
+        }
+        if let Some(pitch) = self.pitch {
+            data.push(("pitch", json!(pitch)));
+        }
+        if let Some(speed) = self.speed {
+            data.push(("speed", json!(speed)));
+        }
+        if let Some(travel_direction) = self.travel_direction {
+            data.push(("travel_direction", json!(travel_direction)));
+        }
+        if let Some(travel_direction_ref) = self.travel_direction_ref {
+            data.push(("travel_direction_ref", json!(travel_direction_ref.as_str())));
+        }
+        if let Some(relative_altitude) = self.relative_altitude {
+            data.push(("relative_altitude", json!(relative_altitude)));
+        }
+        if self.timestamp_utc.is_some()
+            && let Some(timestamp) = &self.timestamp
+        {
+            // <TimeStamp> holds the UTC time then, so keep the local one here
+            data.push(("timestamp_local", json!(timestamp)));
+        }
+        if let Some(offset) = &self.timestamp_offset {
+            data.push(("timestamp_offset", json!(offset)));
+        }
+        if let Some(source) = self.timestamp_utc_source {
+            data.push(("timestamp_utc_source", json!(source.as_str())));
+        }
+        if let Some(source) = self.timestamp_source {
+            data.push(("timestamp_source", json!(source.as_str())));
+        }
+        if let Some(timestamp_gps) = &self.timestamp_gps {
+            data.push(("timestamp_gps", json!(timestamp_gps)));
+        }
+        if !self.missing_tags.is_empty() {
+            data.push(("missing_tags", json!(self.missing_tags)));
+        }
+        if let Some(camera) = &self.camera {
+            data.append(&mut camera.properties());
+        }
+        if let Some(accuracy) = &self.accuracy {
+            data.append(&mut accuracy.properties());
+        }
+        if let Some(description) = &self.description {
+            data.append(&mut description.properties());
+        }
+        if let Some(object) = &self.object {
+            data.append(&mut object.properties());
+        }
+        if self.low_accuracy {
+            data.push(("low_accuracy", json!(true)));
+        }
+        data.append(&mut self.validity.properties());
+        if let Some(source) = self.location_source {
+            data.push(("location_source", json!(source.as_str())));
+        }
+        if self.media_type != MediaType::Image {
+            data.push(("media_type", json!(self.media_type.as_str())));
+        }
+        if let Some(mime_type) = &self.mime_type {
+            data.push(("mime_type", json!(mime_type)));
+        }
+        if let Some(map_datum) = &self.map_datum {
+            data.push(("map_datum", json!(map_datum)));
+        }
+        data
+    }
+
+    /// Reads back the `<ExtendedData>` written by `as_kml`. All values are strings.
+    fn kml_data_from_placemark(pm: &kml::types::Placemark) -> Map<String, serde_json::Value> {
+        pm.children
+            .iter()
+            .filter(|e| e.name == "ExtendedData")
+            .flat_map(|e| e.children.iter())
+            .filter(|e| e.name == "Data")
+            .filter_map(|e| {
+                let name = e.attrs.get("name")?.to_owned();
+                let value = e.children.iter().find(|c| c.name == "value")?;
+                Some((name, json!(value.content.to_owned().unwrap_or_default())))
+            })
+            .collect()
+    }
+
+    /// The `<when>` of the placemark's `<TimeStamp>`, as written by `as_kml`.
+    fn kml_when_from_placemark(pm: &kml::types::Placemark) -> Option<String> {
+        pm.children
+            .iter()
+            .filter(|e| e.name == "TimeStamp")
+            .flat_map(|e| e.children.iter())
+            .find(|e| e.name == "when")?
+            .content
+            .to_owned()
+    }
+
     pub fn as_geojson(&self) -> String {
         let mut j = json!({
             "type": "Feature",
@@ -63,12 +856,86 @@ impl FileLocation {
         if let Some(direction) = self.direction {
             j["properties"]["direction"] = json!(direction);
         }
+        if let Some(direction_ref) = self.direction_ref {
+            j["properties"]["direction_ref"] = json!(direction_ref.as_str());
+        }
+        if let Some(pitch) = self.pitch {
+            j["properties"]["pitch"] = json!(pitch);
+        }
+        if let Some(speed) = self.speed {
+            j["properties"]["speed"] = json!(speed);
+        }
+        if let Some(travel_direction) = self.travel_direction {
+            j["properties"]["travel_direction"] = json!(travel_direction);
+        }
+        if let Some(travel_direction_ref) = self.travel_direction_ref {
+            j["properties"]["travel_direction_ref"] = json!(travel_direction_ref.as_str());
+        }
+        if let Some(relative_altitude) = self.relative_altitude {
+            j["properties"]["relative_altitude"] = json!(relative_altitude);
+        }
         if let Some(timestamp) = &self.timestamp {
             j["properties"]["timestamp"] = json!(timestamp);
         }
+        if let Some(offset) = &self.timestamp_offset {
+            j["properties"]["timestamp_offset"] = json!(offset);
+        }
+        if let Some(timestamp_utc) = &self.timestamp_utc {
+            j["properties"]["timestamp_utc"] = json!(timestamp_utc);
+        }
+        if let Some(source) = self.timestamp_utc_source {
+            j["properties"]["timestamp_utc_source"] = json!(source.as_str());
+        }
+        if let Some(source) = self.timestamp_source {
+            j["properties"]["timestamp_source"] = json!(source.as_str());
+        }
+        if let Some(timestamp_gps) = &self.timestamp_gps {
+            j["properties"]["timestamp_gps"] = json!(timestamp_gps);
+        }
         if let Some(base64) = &self.thumbnail {
             j["properties"]["thumbnail"] = json!(base64)
         }
+        if !self.missing_tags.is_empty() {
+            j["properties"]["missing_tags"] = json!(self.missing_tags);
+        }
+        if let Some(camera) = &self.camera {
+            for (key, value) in camera.properties() {
+                j["properties"][key] = value;
+            }
+        }
+        if let Some(accuracy) = &self.accuracy {
+            for (key, value) in accuracy.properties() {
+                j["properties"][key] = value;
+            }
+        }
+        if let Some(description) = &self.description {
+            for (key, value) in description.properties() {
+                j["properties"][key] = value;
+            }
+        }
+        if let Some(object) = &self.object {
+            for (key, value) in object.properties() {
+                j["properties"][key] = value;
+            }
+        }
+        if self.low_accuracy {
+            j["properties"]["low_accuracy"] = json!(true);
+        }
+        for (key, value) in self.validity.properties() {
+            j["properties"][key] = value;
+        }
+        if let Some(source) = self.location_source {
+            j["properties"]["location_source"] = json!(source.as_str());
+        }
+        if self.media_type != MediaType::Image {
+            j["properties"]["media_type"] = json!(self.media_type.as_str());
+        }
+        if let Some(mime_type) = &self.mime_type {
+            j["properties"]["mime_type"] = json!(mime_type);
+        }
+        if let Some(map_datum) = &self.map_datum {
+            j["properties"]["map_datum"] = json!(map_datum);
+        }
         j.to_string()
     }
 
@@ -79,10 +946,22 @@ impl FileLocation {
     }
 
     fn get_thumbnail_base64(&self) -> Option<String> {
-        let file = File::open(&self.file).ok()?;
-        let reader = BufReader::new(file);
-        let thumbnail =
-            create_thumbnails(reader, mime::IMAGE_JPEG, [ThumbnailSize::Medium]).ok()?;
+        let mut reader = BufReader::new(File::open(&self.file).ok()?);
+        // Video frames would need ffmpeg, so only images are decoded
+        let mime = self
+            .mime_type
+            .as_deref()
+            .and_then(|m| m.parse::<mime::Mime>().ok())
+            .filter(|m| m.type_() == mime::IMAGE)
+            .unwrap_or(mime::IMAGE_JPEG);
+        // Formats the image decoder does not support (eg HEIC, AVIF) often still carry
+        // a small JPEG preview in their EXIF block.
+        let thumbnail = create_thumbnails(&mut reader, mime, [ThumbnailSize::Medium])
+            .ok()
+            .or_else(|| {
+                let jpeg = Self::exif_thumbnail(&Self::read_exif(&mut reader)?)?;
+                create_thumbnails(Cursor::new(jpeg), mime::IMAGE_JPEG, [ThumbnailSize::Medium]).ok()
+            })?;
         let thumbnail = thumbnail.first()?.to_owned();
         let mut buf = Cursor::new(Vec::new());
         thumbnail.write_jpeg(&mut buf, 8).ok()?;
@@ -91,18 +970,65 @@ impl FileLocation {
         Some(encoded)
     }
 
+    /// The JPEG preview stored in the thumbnail IFD (IFD1) of an EXIF block, if any.
+    fn exif_thumbnail(exif: &Exif) -> Option<Vec<u8>> {
+        let field = |tag: Tag| exif.get_field(tag, In::THUMBNAIL)?.value.get_uint(0);
+        let offset = field(Tag::JPEGInterchangeFormat)? as usize;
+        let length = field(Tag::JPEGInterchangeFormatLength)? as usize;
+        Some(
+            exif.buf()
+                .get(offset..offset.checked_add(length)?)?
+                .to_vec(),
+        )
+    }
+
     pub fn from_kml_element(element: &Kml) -> Option<Self> {
         if let Kml::Placemark(pm) = element
             && let (Some(name), Some(kml::types::Geometry::Point(point))) = (&pm.name, &pm.geometry)
         {
+            let data = Self::kml_data_from_placemark(pm);
+            // `as_kml` puts the UTC time into <TimeStamp> if there is one, and the local
+            // time into `timestamp_local` then
+            let when = Self::kml_when_from_placemark(pm);
+            let (timestamp, timestamp_utc) = match property_string(&data, "timestamp_local") {
+                Some(local) => (Some(local), when),
+                None => (when.map(|w| w.replacen('T', " ", 1)), None),
+            };
             return Some(Self {
-                file: name.to_owned(),
+                file: property_string(&data, "file").unwrap_or_else(|| name.to_owned()),
                 latitude: point.coord.y,
                 longitude: point.coord.x,
                 altitude: point.coord.z,
-                direction: None, // Not encoded in KML
-                thumbnail: None,
-                timestamp: None,
+                direction: property_f64(&data, "direction"),
+                direction_ref: property_direction_ref(&data, "direction_ref"),
+                pitch: property_f64(&data, "pitch"),
+                speed: property_f64(&data, "speed"),
+                travel_direction: property_f64(&data, "travel_direction"),
+                travel_direction_ref: property_direction_ref(&data, "travel_direction_ref"),
+                relative_altitude: property_f64(&data, "relative_altitude"),
+                timestamp,
+                timestamp_offset: property_string(&data, "timestamp_offset"),
+                timestamp_utc,
+                timestamp_utc_source: property_string(&data, "timestamp_utc_source")
+                    .and_then(|s| UtcSource::from_name(&s)),
+                timestamp_source: property_string(&data, "timestamp_source")
+                    .and_then(|s| TimestampSource::from_name(&s)),
+                timestamp_gps: property_string(&data, "timestamp_gps"),
+                missing_tags: property_strings(&data, "missing_tags"),
+                camera: CameraInfo::from_properties(&data),
+                accuracy: GpsAccuracy::from_properties(&data),
+                description: Description::from_properties(&data),
+                object: ObjectLocation::from_properties(&data),
+                mime_type: property_string(&data, "mime_type"),
+                map_datum: property_string(&data, "map_datum"),
+                low_accuracy: property_string(&data, "low_accuracy").is_some_and(|s| s == "true"),
+                validity: Validity::from_properties(&data),
+                location_source: property_string(&data, "location_source")
+                    .and_then(|s| LocationSource::from_name(&s)),
+                media_type: property_string(&data, "media_type")
+                    .and_then(|s| MediaType::from_name(&s))
+                    .unwrap_or_default(),
+                ..Default::default()
             });
         }
         None
@@ -134,51 +1060,287 @@ impl FileLocation {
             longitude: *point.first()?,
             altitude: properties.get("altitude").and_then(|v| v.as_f64()),
             direction: properties.get("direction").and_then(|v| v.as_f64()),
+            direction_ref: property_direction_ref(&properties, "direction_ref"),
             thumbnail,
             timestamp,
+            timestamp_offset: property_string(&properties, "timestamp_offset"),
+            timestamp_utc: property_string(&properties, "timestamp_utc"),
+            timestamp_utc_source: property_string(&properties, "timestamp_utc_source")
+                .and_then(|s| UtcSource::from_name(&s)),
+            timestamp_gps: property_string(&properties, "timestamp_gps"),
+            missing_tags: property_strings(&properties, "missing_tags"),
+            camera: CameraInfo::from_properties(&properties),
+            accuracy: GpsAccuracy::from_properties(&properties),
+            low_accuracy: properties
+                .get("low_accuracy")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            validity: Validity::from_properties(&properties),
+            location_source: property_string(&properties, "location_source")
+                .and_then(|s| LocationSource::from_name(&s)),
+            media_type: property_string(&properties, "media_type")
+                .and_then(|s| MediaType::from_name(&s))
+                .unwrap_or_default(),
+            track: None, // A separate feature; attached by `FileSet`
+            pitch: properties.get("pitch").and_then(|v| v.as_f64()),
+            relative_altitude: properties.get("relative_altitude").and_then(|v| v.as_f64()),
+            description: Description::from_properties(&properties),
+            mime_type: property_string(&properties, "mime_type"),
+            timestamp_source: property_string(&properties, "timestamp_source")
+                .and_then(|s| TimestampSource::from_name(&s)),
+            object: ObjectLocation::from_properties(&properties),
+            speed: property_f64(&properties, "speed"),
+            travel_direction: property_f64(&properties, "travel_direction"),
+            travel_direction_ref: property_direction_ref(&properties, "travel_direction_ref"),
+            map_datum: property_string(&properties, "map_datum"),
         })
     }
 
-    fn name_xml_escaped(&self) -> String {
-        self.file
-            .replace('&', "&amp;")
-            .replace('<', "&lt;")
-            .replace('>', "&gt;")
-            .replace('"', "&quot;")
-            .replace('\'', "&apos;")
-    }
-
+    /// Lenient, so timestamps from other tools or older output files still count.
     pub fn timestamp_parsed(&self) -> Option<chrono::NaiveDateTime> {
         self.timestamp
+            .as_deref()
+            .and_then(date_time::parse_date_time)
+    }
+
+    pub fn timestamp_utc_parsed(&self) -> Option<DateTime<Utc>> {
+        self.timestamp_utc
             .as_ref()
-            .and_then(|s| chrono::NaiveDateTime::parse_from_str(s, "%Y:%m:%d %H:%M:%S").ok())
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|dt| dt.with_timezone(&Utc))
+    }
+
+    /// The capture time to compare and sort by: UTC where it is known, so images from
+    /// different time zones are ordered correctly, otherwise the local time.
+    pub fn capture_time(&self) -> Option<NaiveDateTime> {
+        self.timestamp_utc_parsed()
+            .map(|dt| dt.naive_utc())
+            .or_else(|| self.timestamp_parsed())
+    }
+
+    /// Estimated horizontal error in metres, if the image records any fix quality.
+    pub fn estimated_error(&self) -> Option<f64> {
+        self.accuracy.as_ref()?.estimated_error()
     }
 
-    fn from_exif(file: &str, exif: &Exif) -> Option<Self> {
+    fn from_exif(file: &str, exif: &Exif, options: &ReadOptions) -> Option<Self> {
         let lat_ref =
             Self::letter_from_value(&exif.get_field(Tag::GPSLatitudeRef, In::PRIMARY)?.value)?;
         let lon_ref =
             Self::letter_from_value(&exif.get_field(Tag::GPSLongitudeRef, In::PRIMARY)?.value)?;
-        let timestamp = Self::string_from_value(exif.get_field(Tag::DateTimeOriginal, In::PRIMARY));
+        let latitude = Self::lat_from_value(
+            &exif.get_field(Tag::GPSLatitude, In::PRIMARY)?.value,
+            lat_ref,
+        )?;
+        let longitude = Self::lon_from_value(
+            &exif.get_field(Tag::GPSLongitude, In::PRIMARY)?.value,
+            lon_ref,
+        )?;
+        let field = |tag: Tag| exif.get_field(tag, In::PRIMARY);
+        let map_datum = Self::string_from_value(field(Tag::GPSMapDatum))
+            .map(|s| {
+                s.trim_matches(|c: char| c == '\0' || c.is_whitespace())
+                    .to_string()
+            })
+            .filter(|s| !s.is_empty() && Datum::from_name(s) != Some(Datum::Wgs84));
+        if let Some(datum) = &map_datum
+            && Datum::from_name(datum).is_none()
+        {
+            // Also flagged as suspicious by `validate`, but that may be filtered out
+            eprintln!("{file}: unknown map datum {datum:?}, coordinates not converted to WGS 84");
+        }
+        let to_wgs84 =
+            |latitude: f64, longitude: f64| match map_datum.as_deref().and_then(Datum::from_name) {
+                Some(datum) => datum.to_wgs84(latitude, longitude),
+                None => (latitude, longitude),
+            };
+        // The bearing and distance are relative to the camera in its own datum
+        let object = Self::object_from_exif(exif, latitude, longitude).map(|object| {
+            let (latitude, longitude) = to_wgs84(object.latitude, object.longitude);
+            ObjectLocation {
+                latitude,
+                longitude,
+                ..object
+            }
+        });
+        let (latitude, longitude) = to_wgs84(latitude, longitude);
+        let capture_time =
+            CAPTURE_TIME_TAGS
+                .iter()
+                .find_map(|(tag, offset_tag, subsec_tag, source)| {
+                    let local = date_time::parse_date_time(&Self::string_from_value(field(*tag))?)?;
+                    Some((local, *offset_tag, *subsec_tag, *source))
+                });
+        let timestamp_offset = capture_time
+            .and_then(|(_, offset_tag, _, _)| Self::string_from_value(field(offset_tag)))
+            .map(|s| {
+                s.trim_matches(|c: char| c == '\0' || c.is_whitespace())
+                    .to_string()
+            })
+            .filter(|s| s.parse::<FixedOffset>().is_ok());
+        let subsec = capture_time
+            .and_then(|(_, _, subsec_tag, _)| Self::string_from_value(field(subsec_tag)));
+        let gps_time = Self::string_from_value(field(Tag::GPSDateStamp))
+            .zip(field(Tag::GPSTimeStamp))
+            .and_then(|(date, time)| Self::utc_from_gps(&date, &time.value));
+        let utc = match (capture_time, &timestamp_offset) {
+            (Some((local, ..)), Some(offset)) => {
+                Self::utc_from_local(local, offset, subsec.as_deref())
+                    .map(|dt| (dt, UtcSource::Offset))
+            }
+            _ => None,
+        }
+        .or_else(|| gps_time.map(|dt| (dt, UtcSource::Gps)));
+        let timestamp_source = match capture_time {
+            Some((_, _, _, source)) => Some(source),
+            None => gps_time.map(|_| TimestampSource::Gps),
+        };
         Some(Self {
             file: file.to_string(),
-            latitude: Self::lat_from_value(
-                &exif.get_field(Tag::GPSLatitude, In::PRIMARY)?.value,
-                lat_ref,
-            )?,
-            longitude: Self::lon_from_value(
-                &exif.get_field(Tag::GPSLongitude, In::PRIMARY)?.value,
-                lon_ref,
-            )?,
-            altitude: Self::f64_from_value(&exif.get_field(Tag::GPSAltitude, In::PRIMARY)?.value),
-            direction: Self::f64_from_value(
-                &exif.get_field(Tag::GPSImgDirection, In::PRIMARY)?.value,
-            ),
+            latitude,
+            longitude,
+            altitude: field(Tag::GPSAltitude)
+                .and_then(|f| Self::f64_from_value(&f.value))
+                .map(|altitude| {
+                    // GPSAltitudeRef 1 means the altitude is below sea level.
+                    match field(Tag::GPSAltitudeRef).and_then(|f| f.value.get_uint(0)) {
+                        Some(1) => -altitude,
+                        _ => altitude,
+                    }
+                }),
+            direction: field(Tag::GPSImgDirection).and_then(|f| Self::f64_from_value(&f.value)),
+            direction_ref: field(Tag::GPSImgDirectionRef)
+                .and_then(|f| Self::letter_from_value(&f.value))
+                .and_then(DirectionRef::from_letter),
             thumbnail: None,
-            timestamp,
+            timestamp: capture_time.map(|(local, ..)| local.format(EXIF_FORMAT).to_string()),
+            timestamp_offset,
+            timestamp_utc: utc.map(|(dt, _)| dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+            timestamp_utc_source: utc.map(|(_, source)| source),
+            timestamp_gps: gps_time.map(|dt| dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+            missing_tags: OPTIONAL_TAGS
+                .iter()
+                .filter(|tag| field(**tag).is_none())
+                .map(|tag| tag.to_string())
+                .collect(),
+            camera: match options.exif_fields {
+                true => CameraInfo::from_exif(exif),
+                false => None,
+            },
+            accuracy: GpsAccuracy::from_exif(exif),
+            low_accuracy: false,
+            validity: Validity::Ok, // Set by `FileSet`
+            location_source: None,  // Set by the caller
+            media_type: MediaType::Image,
+            track: None,
+            pitch: None, // From XMP, see `with_camera_pose`
+            relative_altitude: None,
+            description: None, // Also from XMP, see `from_embedded`
+            mime_type: None,   // See `from_file_with_options`
+            timestamp_source,
+            object,
+            speed: field(Tag::GPSSpeed)
+                .and_then(|f| Self::f64_from_value(&f.value))
+                .map(|speed| {
+                    // km/h, unless GPSSpeedRef says mph or knots
+                    let unit =
+                        field(Tag::GPSSpeedRef).and_then(|f| Self::letter_from_value(&f.value));
+                    match unit.map(|c| c.to_ascii_uppercase()) {
+                        Some('M') => speed * 0.44704,
+                        Some('N') => speed * 1852.0 / 3600.0,
+                        _ => speed / 3.6,
+                    }
+                }),
+            travel_direction: field(Tag::GPSTrack).and_then(|f| Self::f64_from_value(&f.value)),
+            travel_direction_ref: field(Tag::GPSTrackRef)
+                .and_then(|f| Self::letter_from_value(&f.value))
+                .and_then(DirectionRef::from_letter),
+            map_datum,
         })
     }
 
+    /// The photographed object's position, from `GPSDestLatitude`/`GPSDestLongitude`, or
+    /// else from the camera position, `GPSDestBearing` and `GPSDestDistance`.
+    fn object_from_exif(exif: &Exif, latitude: f64, longitude: f64) -> Option<ObjectLocation> {
+        let field = |tag: Tag| exif.get_field(tag, In::PRIMARY).map(|f| &f.value);
+        let letter = |tag: Tag| field(tag).and_then(Self::letter_from_value);
+        let bearing = field(Tag::GPSDestBearing).and_then(Self::f64_from_value);
+        let bearing_ref = letter(Tag::GPSDestBearingRef).and_then(DirectionRef::from_letter);
+        let distance = field(Tag::GPSDestDistance)
+            .and_then(Self::f64_from_value)
+            .map(|distance| {
+                // Kilometres, unless GPSDestDistanceRef says miles or nautical miles
+                match letter(Tag::GPSDestDistanceRef).map(|c| c.to_ascii_uppercase()) {
+                    Some('M') => distance * 1609.344,
+                    Some('N') => distance * 1852.0,
+                    _ => distance * 1000.0,
+                }
+            });
+        let position = field(Tag::GPSDestLatitude)
+            .zip(letter(Tag::GPSDestLatitudeRef))
+            .and_then(|(v, r)| Self::lat_from_value(v, r))
+            .zip(
+                field(Tag::GPSDestLongitude)
+                    .zip(letter(Tag::GPSDestLongitudeRef))
+                    .and_then(|(v, r)| Self::lon_from_value(v, r)),
+            );
+        match position {
+            Some((latitude, longitude)) => Some(ObjectLocation {
+                latitude,
+                longitude,
+                bearing,
+                bearing_ref,
+                distance,
+            }),
+            None => {
+                ObjectLocation::from_bearing(latitude, longitude, bearing?, bearing_ref, distance?)
+            }
+        }
+    }
+
+    fn utc_from_local(
+        local: NaiveDateTime,
+        offset: &str,
+        subsec: Option<&str>,
+    ) -> Option<DateTime<Utc>> {
+        let offset = offset.parse::<FixedOffset>().ok()?;
+        let local = local + Self::subsec_duration(subsec);
+        Some(
+            local
+                .and_local_timezone(offset)
+                .single()?
+                .with_timezone(&Utc),
+        )
+    }
+
+    /// `SubSecTime*` holds the decimal digits of the fractional second, eg "125".
+    fn subsec_duration(subsec: Option<&str>) -> TimeDelta {
+        let digits: String = subsec
+            .unwrap_or_default()
+            .trim()
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .take(9)
+            .collect();
+        let nanos = format!("{digits:0<9}").parse().unwrap_or(0);
+        TimeDelta::nanoseconds(nanos)
+    }
+
+    fn utc_from_gps(date: &str, time: &Value) -> Option<DateTime<Utc>> {
+        let date = NaiveDate::parse_from_str(date.trim(), "%Y:%m:%d").ok()?;
+        let time = match time {
+            Value::Rational(r) if r.len() == 3 => r,
+            _ => return None,
+        };
+        let seconds = time[0].to_f64() * 3600.0 + time[1].to_f64() * 60.0 + time[2].to_f64();
+        if !seconds.is_finite() || !(0.0..86400.0).contains(&seconds) {
+            return None;
+        }
+        let millis = (seconds * 1000.0).round() as i64;
+        let dt = date.and_hms_opt(0, 0, 0)? + TimeDelta::milliseconds(millis);
+        Some(dt.and_utc())
+    }
+
     fn string_from_value(f: Option<&exif::Field>) -> Option<String> {
         if let Some(f) = f
             && let Value::Ascii(vs) = &f.value
@@ -199,32 +1361,154 @@ impl FileLocation {
 
     fn f64_from_value(v: &Value) -> Option<f64> {
         match v {
-            Value::Rational(r) => Some(r.first()?.to_f64()),
+            // A zero denominator yields NaN or infinity, which JSON cannot represent
+            Value::Rational(r) => Some(r.first()?.to_f64()).filter(|f| f.is_finite()),
             _ => None,
         }
     }
 
     fn lat_from_value(v: &Value, r: char) -> Option<f64> {
-        latlon::parse_lat(Self::coord_string_from_value(v, r)?).ok()
+        let sign = match r.to_ascii_uppercase() {
+            'N' => 1.0,
+            'S' => -1.0,
+            _ => return None,
+        };
+        Some(sign * Self::degrees_from_value(v)?)
     }
 
     fn lon_from_value(v: &Value, r: char) -> Option<f64> {
-        latlon::parse_lng(Self::coord_string_from_value(v, r)?).ok()
+        let sign = match r.to_ascii_uppercase() {
+            'E' => 1.0,
+            'W' => -1.0,
+            _ => return None,
+        };
+        Some(sign * Self::degrees_from_value(v)?)
     }
 
-    fn coord_string_from_value(v: &Value, r: char) -> Option<String> {
-        let v = match v {
-            Value::Rational(r) => r,
+    /// Converts EXIF degrees[, minutes[, seconds]] rationals to decimal degrees. Some
+    /// cameras write decimal degrees with fewer components, or `0/0` for unused ones.
+    /// The sum is built as a single fraction, so only the final division rounds.
+    fn degrees_from_value(v: &Value) -> Option<f64> {
+        const SCALE: [u128; 3] = [1, 60, 3600];
+        let parts = match v {
+            Value::Rational(r) if (1..=3).contains(&r.len()) => r,
             _ => return None,
         };
-        let d = v.first()?.to_f64();
-        let m = v.get(1)?.to_f64();
-        let s = v.get(2)?.to_f64();
-        let s = format!("{d}° {m}′ {s}″ {r}");
-        Some(s)
+        let mut num: u128 = 0;
+        let mut den: u128 = 1;
+        for (part, scale) in parts.iter().zip(SCALE) {
+            match (part.num, part.denom) {
+                (0, _) => continue,
+                (_, 0) => return None,
+                _ => {}
+            }
+            let part_den = part.denom as u128 * scale;
+            num = num
+                .checked_mul(part_den)?
+                .checked_add(part.num as u128 * den)?;
+            den = den.checked_mul(part_den)?;
+            let divisor = gcd(num, den);
+            num /= divisor;
+            den /= divisor;
+        }
+        Some(num as f64 / den as f64)
     }
 }
 
+/// A text tag of the primary image, trimmed; `None` if it is empty.
+pub(crate) fn exif_string(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(vs) => {
+            let s = std::str::from_utf8(vs.first()?).ok()?.trim();
+            (!s.is_empty()).then(|| s.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// The first value of a rational tag of the primary image; `None` for a zero
+/// denominator.
+pub(crate) fn exif_rational(exif: &Exif, tag: Tag) -> Option<f64> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Rational(r) => Some(r.first()?.to_f64()).filter(|f| f.is_finite()),
+        _ => None,
+    }
+}
+
+/// Reads a string property. KML `<ExtendedData>` values are always strings, so numbers
+/// are accepted too, and vice versa in `property_f64`/`property_u32`.
+pub(crate) fn property_string(
+    properties: &Map<String, serde_json::Value>,
+    key: &str,
+) -> Option<String> {
+    match properties.get(key)? {
+        serde_json::Value::String(s) => Some(s.to_owned()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+pub(crate) fn property_f64(properties: &Map<String, serde_json::Value>, key: &str) -> Option<f64> {
+    match properties.get(key)? {
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        v => v.as_f64(),
+    }
+}
+
+pub(crate) fn property_u32(properties: &Map<String, serde_json::Value>, key: &str) -> Option<u32> {
+    match properties.get(key)? {
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        v => v.as_u64().and_then(|n| u32::try_from(n).ok()),
+    }
+}
+
+/// A list of strings: an array in GeoJSON, and a JSON array in a string in KML, whose
+/// `<ExtendedData>` only holds text.
+pub(crate) fn property_strings(
+    properties: &Map<String, serde_json::Value>,
+    key: &str,
+) -> Vec<String> {
+    match properties.get(key) {
+        Some(serde_json::Value::String(s)) => serde_json::from_str(s).unwrap_or_default(),
+        Some(v) => serde_json::from_value(v.clone()).unwrap_or_default(),
+        None => vec![],
+    }
+}
+
+/// `T` or `M`, or a word starting with either.
+pub(crate) fn property_direction_ref(
+    properties: &Map<String, serde_json::Value>,
+    key: &str,
+) -> Option<DirectionRef> {
+    DirectionRef::from_letter(property_string(properties, key)?.chars().next()?)
+}
+
+/// Adds an output property, unless there is no value.
+pub(crate) fn push_property(
+    properties: &mut Vec<(&'static str, serde_json::Value)>,
+    key: &'static str,
+    value: Option<impl Serialize>,
+) {
+    if let Some(value) = value {
+        properties.push((key, json!(value)));
+    }
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1)
+}
+
+pub(crate) fn xml_escaped(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -239,8 +1523,727 @@ mod tests {
         assert_eq!(fl.longitude, 12.341161111111111);
         assert_eq!(fl.altitude, Some(46.79));
         assert_eq!(fl.direction, Some(11.0));
+        assert_eq!(fl.direction_ref, Some(DirectionRef::Magnetic));
         assert_eq!(fl.thumbnail, None);
         assert_eq!(fl.timestamp, Some("2025:03:06 05:41:42".to_string()));
+        assert_eq!(fl.timestamp_offset, Some("+01:00".to_string()));
+        assert_eq!(
+            fl.timestamp_utc,
+            Some("2025-03-06T04:41:42.125Z".to_string())
+        );
+        assert_eq!(fl.timestamp_utc_source, Some(UtcSource::Offset));
+        assert_eq!(fl.timestamp_gps, Some("2025-03-06T05:41:36Z".to_string()));
+        assert!(fl.missing_tags.is_empty());
+        assert_eq!(fl.mime_type, Some("image/jpeg".to_string()));
+    }
+
+    /// Both fixtures hold sunrise.jpg's EXIF block in a HEIF container. The HEIC one is
+    /// read by the EXIF crate; the AVIF one lacks the `mif1` brand and needs our parser.
+    #[test]
+    fn test_from_file_heif() {
+        for (file, mime_type) in [
+            ("test_files/sunrise.heic", "image/heif"),
+            ("test_files/sunrise.avif", "image/avif"),
+        ] {
+            let fl = FileLocation::from_file(file).unwrap();
+            assert_eq!(fl.mime_type.as_deref(), Some(mime_type));
+            assert_eq!(fl.latitude, 45.50566666666667);
+            assert_eq!(fl.longitude, 12.341161111111111);
+            assert_eq!(fl.timestamp, Some("2025:03:06 05:41:42".to_string()));
+        }
+    }
+
+    /// The raw fixtures carry sunrise.jpg's EXIF in each raw family's layout: plain TIFF
+    /// (DNG), TIFF with a vendor magic (ORF, RW2), a JPEG preview (RAF) and CMT boxes (CR3).
+    #[test]
+    fn test_from_file_raw() {
+        let options = ReadOptions {
+            exif_fields: true,
+            ..Default::default()
+        };
+        for ext in ["dng", "orf", "rw2", "raf", "cr3"] {
+            let file = format!("test_files/sunrise.{ext}");
+            let fl = FileLocation::from_file_with_options(&file, &options).unwrap();
+            assert_eq!(fl.latitude, 45.50566666666667, "{file}");
+            assert_eq!(fl.longitude, 12.341161111111111, "{file}");
+            assert_eq!(
+                fl.timestamp,
+                Some("2025:03:06 05:41:42".to_string()),
+                "{file}"
+            );
+            let camera = fl.camera.unwrap();
+            assert_eq!(camera.model, Some("Pixel 8 Pro".to_string()), "{file}");
+            assert_eq!(camera.iso, Some(18), "{file}");
+        }
+    }
+
+    #[test]
+    fn test_from_file_png_webp() {
+        for file in ["test_files/sunrise.png", "test_files/sunrise.webp"] {
+            let fl = FileLocation::from_file(file).unwrap();
+            assert_eq!(fl.latitude, 45.50566666666667, "{file}");
+            assert_eq!(fl.longitude, 12.341161111111111, "{file}");
+            assert_eq!(fl.altitude, Some(46.79), "{file}");
+        }
+    }
+
+    /// This PNG has no EXIF at all, only an XMP packet with the same position.
+    #[test]
+    fn test_from_file_xmp_only() {
+        let fl = FileLocation::from_file("test_files/sunrise_xmp.png").unwrap();
+        assert_eq!(fl.latitude, 45.50566666666667);
+        assert_eq!(fl.longitude, 12.341161111111111);
+        assert_eq!(fl.altitude, Some(46.79));
+        assert_eq!(fl.timestamp, Some("2025:03:06 05:41:42".to_string()));
+        assert_eq!(
+            fl.timestamp_utc,
+            Some("2025-03-06T04:41:42.125Z".to_string())
+        );
+    }
+
+    /// sunrise.mov carries the Apple metadata keys, sunrise.mp4 only `©xyz` and the
+    /// movie header time.
+    #[test]
+    fn test_from_file_video() {
+        let fl = FileLocation::from_file("test_files/sunrise.mov").unwrap();
+        assert_eq!((fl.latitude, fl.longitude), (45.5057, 12.3412));
+        assert_eq!(fl.altitude, Some(46.79));
+        assert_eq!(fl.timestamp, Some("2025:03:06 05:41:42".to_string()));
+        assert_eq!(fl.timestamp_offset, Some("+01:00".to_string()));
+        assert_eq!(fl.timestamp_utc, Some("2025-03-06T04:41:42Z".to_string()));
+        assert_eq!(fl.media_type, MediaType::Video);
+        assert_eq!(fl.location_source, Some(LocationSource::QuickTime));
+        assert!(fl.as_geojson().contains(r#""media_type":"video""#));
+
+        let fl = FileLocation::from_file("test_files/sunrise.mp4").unwrap();
+        assert_eq!((fl.latitude, fl.longitude), (45.5057, 12.3412));
+        assert_eq!(fl.altitude, None);
+        assert_eq!(fl.timestamp, None);
+        assert_eq!(fl.timestamp_utc, Some("2025-03-06T05:41:42Z".to_string()));
+        assert_eq!(fl.timestamp_utc_source, Some(UtcSource::Container));
+        assert_eq!(fl.media_type, MediaType::Video);
+    }
+
+    /// A drone photo with its position and gimbal orientation only in XMP.
+    #[test]
+    fn test_from_file_drone_xmp() {
+        let fl = FileLocation::from_file("test_files/sunrise_dji.jpg").unwrap();
+        assert_eq!(fl.location_source, Some(LocationSource::Xmp));
+        assert_eq!(fl.altitude, Some(76.89));
+        assert_eq!(fl.direction, Some(255.7)); // Gimbal yaw -104.3
+        assert_eq!(fl.direction_ref, None);
+        assert_eq!(fl.pitch, Some(-45.0));
+        assert_eq!(fl.relative_altitude, Some(30.1));
+        let geojson = fl.as_geojson();
+        assert!(geojson.contains(r#""pitch":-45.0"#));
+        assert!(geojson.contains(r#""relative_altitude":30.1"#));
+
+        // Images without drone XMP are unchanged
+        let fl = FileLocation::from_file("test_files/sunrise.jpg").unwrap();
+        assert_eq!(fl.direction, Some(11.0));
+        assert_eq!((fl.pitch, fl.relative_altitude), (None, None));
+    }
+
+    /// sunrise_iptc.jpg has an IPTC-IIM block and an XMP packet, which wins for the
+    /// city.
+    #[test]
+    fn test_from_file_description() {
+        let fl = FileLocation::from_file("test_files/sunrise_iptc.jpg").unwrap();
+        let description = fl.description.as_ref().unwrap();
+        assert_eq!(
+            description.title.as_deref(),
+            Some("Sunrise over the lagoon")
+        );
+        assert_eq!(description.caption.as_deref(), Some("Alba sulla laguna"));
+        assert_eq!(description.keywords, ["sunrise", "lagoon"]);
+        assert_eq!(description.city.as_deref(), Some("Venice"));
+        assert_eq!(description.country.as_deref(), Some("Italy"));
+        let geojson = fl.as_geojson();
+        assert!(geojson.contains(r#""keywords":["sunrise","lagoon"]"#));
+        assert!(fl.as_kml().contains("<name>Sunrise over the lagoon</name>"));
+
+        let fl = FileLocation::from_file("test_files/sunrise.jpg").unwrap();
+        assert_eq!(fl.description, None);
+    }
+
+    /// sunrise_takeout.jpg has no metadata; its sidecar has the truncated name newer
+    /// Takeout exports use.
+    #[test]
+    fn test_from_file_takeout() {
+        let fl = FileLocation::from_file("test_files/sunrise_takeout.jpg").unwrap();
+        assert_eq!((fl.latitude, fl.longitude), (45.5056667, 12.3411611));
+        assert_eq!(fl.altitude, Some(46.79));
+        assert_eq!(fl.timestamp, None);
+        assert_eq!(fl.timestamp_utc, Some("2025-03-06T04:41:42Z".to_string()));
+        assert_eq!(fl.timestamp_utc_source, Some(UtcSource::Takeout));
+        assert_eq!(fl.location_source, Some(LocationSource::Takeout));
+        assert!(fl.as_geojson().contains(r#""location_source":"takeout""#));
+
+        let options = ReadOptions {
+            sidecars: SidecarMode::Ignore,
+            ..Default::default()
+        };
+        assert!(
+            FileLocation::from_file_with_options("test_files/sunrise_takeout.jpg", &options)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_from_file_dji_srt() {
+        let fl = FileLocation::from_file("test_files/sunrise.srt").unwrap();
+        assert_eq!((fl.latitude, fl.longitude), (45.505667, 12.341161));
+        assert_eq!(fl.altitude, Some(76.89));
+        assert_eq!(fl.timestamp, Some("2025:03:06 05:41:42".to_string()));
+        assert_eq!(fl.timestamp_utc, None);
+        assert_eq!(fl.location_source, Some(LocationSource::DjiSrt));
+        assert_eq!(fl.media_type, MediaType::Video);
+        let track = fl.track.unwrap();
+        assert_eq!(track.points.len(), 3);
+        assert_eq!(track.start, Some("2025-03-06T05:41:42.125".to_string()));
+        assert_eq!(track.end, Some("2025-03-06T05:41:42.192".to_string()));
+    }
+
+    /// Two one-second GPMF samples with two GPS5 fixes each.
+    #[test]
+    fn test_from_file_gopro() {
+        let fl = FileLocation::from_file("test_files/sunrise_gopro.mp4").unwrap();
+        assert_eq!(fl.latitude, 45.5056667); // GPS5 has seven decimals
+        assert_eq!(fl.longitude, 12.3411611);
+        assert_eq!(fl.altitude, Some(46.79));
+        assert_eq!(fl.timestamp_utc, Some("2025-03-06T05:41:36Z".to_string()));
+        assert_eq!(fl.location_source, Some(LocationSource::Gpmf));
+        assert_eq!(fl.media_type, MediaType::Video);
+        let track = fl.track.unwrap();
+        assert_eq!(track.points.len(), 4);
+        assert_eq!(track.points[3].latitude, 45.50578);
+        assert_eq!(track.start, Some("2025-03-06T05:41:36Z".to_string()));
+        assert_eq!(track.end, Some("2025-03-06T05:41:37.500Z".to_string()));
+    }
+
+    /// sunrise.png.xmp moves sunrise.png to Venice and sets a true direction.
+    #[test]
+    fn test_sidecar_precedence() {
+        let read = |sidecars| {
+            let options = ReadOptions {
+                sidecars,
+                ..Default::default()
+            };
+            FileLocation::from_file_with_options("test_files/sunrise.png", &options).unwrap()
+        };
+        for sidecars in [SidecarMode::Fallback, SidecarMode::Ignore] {
+            let fl = read(sidecars);
+            assert_eq!(fl.latitude, 45.50566666666667);
+            assert_eq!(fl.location_source, Some(LocationSource::Exif));
+        }
+        let fl = read(SidecarMode::Prefer);
+        assert_eq!(fl.latitude, 45.4343);
+        assert_eq!(fl.longitude, 12.3388);
+        assert_eq!(fl.altitude, Some(46.79)); // Not in the sidecar
+        assert_eq!(fl.direction, Some(270.0));
+        assert_eq!(fl.direction_ref, Some(DirectionRef::True));
+        assert_eq!(fl.timestamp, Some("2025:03:06 05:41:42".to_string()));
+        assert_eq!(fl.location_source, Some(LocationSource::XmpSidecar));
+    }
+
+    /// The sidecar's rating and keywords are used even if its position is not.
+    #[test]
+    fn test_sidecar_description() {
+        let read = |sidecars| {
+            let options = ReadOptions {
+                sidecars,
+                ..Default::default()
+            };
+            FileLocation::from_file_with_options("test_files/sunrise.png", &options)
+                .unwrap()
+                .description
+        };
+        for sidecars in [SidecarMode::Fallback, SidecarMode::Prefer] {
+            let description = read(sidecars).unwrap();
+            assert_eq!(description.rating, Some(5));
+            assert_eq!(description.label, Some("Green".to_string()));
+            assert_eq!(description.keywords, ["sunrise", "lagoon"]);
+        }
+        assert_eq!(read(SidecarMode::Ignore), None);
+    }
+
+    /// A sidecar named after the stem, for a file with no position of its own.
+    #[test]
+    fn test_sidecar_fallback() {
+        let dir = std::env::temp_dir();
+        let image = dir.join("img_coords_test_sidecar.jpg");
+        let sidecar = dir.join("img_coords_test_sidecar.xmp");
+        std::fs::write(&image, b"no metadata here").unwrap();
+        std::fs::copy("test_files/sunrise.png.xmp", &sidecar).unwrap();
+        let image = image.to_str().unwrap();
+        let fl = FileLocation::from_file(image).unwrap();
+        assert_eq!(fl.latitude, 45.4343);
+        assert_eq!(fl.location_source, Some(LocationSource::XmpSidecar));
+        let options = ReadOptions {
+            sidecars: SidecarMode::Ignore,
+            ..Default::default()
+        };
+        assert!(FileLocation::from_file_with_options(image, &options).is_none());
+        std::fs::remove_file(image).unwrap();
+        std::fs::remove_file(sidecar).unwrap();
+    }
+
+    /// No decodable image and no EXIF preview: no thumbnail, but no failure either.
+    #[test]
+    fn test_thumbnail_heif_without_preview() {
+        let mut fl = FileLocation::from_file("test_files/sunrise.heic").unwrap();
+        fl.generate_missing_thumbnail();
+        assert_eq!(fl.thumbnail, None);
+    }
+
+    #[test]
+    fn test_exif_thumbnail() {
+        let jpeg = b"\xff\xd8fake jpeg\xff\xd9";
+        let fields = gps_fields();
+        let mut writer = exif::experimental::Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        writer.set_jpeg(jpeg, In::THUMBNAIL);
+        let mut buf = Cursor::new(Vec::new());
+        writer.write(&mut buf, false).unwrap();
+        let exif = exif::Reader::new().read_raw(buf.into_inner()).unwrap();
+        assert_eq!(FileLocation::exif_thumbnail(&exif), Some(jpeg.to_vec()));
+        assert_eq!(
+            FileLocation::exif_thumbnail(&exif_from_fields(&fields)),
+            None
+        );
+    }
+
+    fn ascii_field(tag: Tag, s: &str) -> exif::Field {
+        exif::Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![s.as_bytes().to_vec()]),
+        }
+    }
+
+    fn rational_field(tag: Tag, parts: &[(u32, u32)]) -> exif::Field {
+        exif::Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value: Value::Rational(parts.iter().map(|r| exif::Rational::from(*r)).collect()),
+        }
+    }
+
+    /// Serializes `fields` with the EXIF writer and reads them back, giving a real `Exif`
+    /// without needing a test image for every tag combination.
+    fn exif_from_fields(fields: &[exif::Field]) -> Exif {
+        let mut writer = exif::experimental::Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut buf = Cursor::new(Vec::new());
+        writer.write(&mut buf, false).unwrap();
+        exif::Reader::new().read_raw(buf.into_inner()).unwrap()
+    }
+
+    fn gps_fields() -> Vec<exif::Field> {
+        vec![
+            ascii_field(Tag::GPSLatitudeRef, "N"),
+            rational_field(Tag::GPSLatitude, &[(45, 1), (30, 1), (2040, 100)]),
+            ascii_field(Tag::GPSLongitudeRef, "E"),
+            rational_field(Tag::GPSLongitude, &[(12, 1), (20, 1), (2818, 100)]),
+        ]
+    }
+
+    /// Images with a position but no altitude, direction or capture time must still be
+    /// kept, with the absent tags recorded.
+    #[test]
+    fn test_from_exif_without_optional_tags() {
+        let exif = exif_from_fields(&gps_fields());
+        let fl = FileLocation::from_exif("x.jpg", &exif, &ReadOptions::default()).unwrap();
+        assert!((fl.latitude - 45.50566666666667).abs() < 1e-9);
+        assert!((fl.longitude - 12.341161111111111).abs() < 1e-9);
+        assert_eq!(fl.altitude, None);
+        assert_eq!(fl.direction, None);
+        assert_eq!(fl.timestamp, None);
+        assert_eq!(
+            fl.missing_tags,
+            vec!["GPSAltitude", "GPSImgDirection", "DateTimeOriginal"]
+        );
+    }
+
+    #[test]
+    fn test_from_exif_partial_optional_tags() {
+        let mut fields = gps_fields();
+        fields.push(rational_field(Tag::GPSAltitude, &[(4679, 100)]));
+        let fl =
+            FileLocation::from_exif("x.jpg", &exif_from_fields(&fields), &ReadOptions::default())
+                .unwrap();
+        assert_eq!(fl.altitude, Some(46.79));
+        assert_eq!(fl.missing_tags, vec!["GPSImgDirection", "DateTimeOriginal"]);
+    }
+
+    fn degrees(parts: &[(u32, u32)], r: char) -> Option<f64> {
+        let v = Value::Rational(parts.iter().map(|r| exif::Rational::from(*r)).collect());
+        match r {
+            'N' | 'S' => FileLocation::lat_from_value(&v, r),
+            _ => FileLocation::lon_from_value(&v, r),
+        }
+    }
+
+    /// Values as written by various cameras and phones.
+    #[test]
+    fn test_degrees_from_rationals() {
+        // Pixel 8 Pro (sunrise.jpg)
+        assert_eq!(
+            degrees(&[(45, 1), (30, 1), (2040, 100)], 'N'),
+            Some(45.50566666666667)
+        );
+        // iPhone: seconds with a high denominator
+        assert_eq!(
+            degrees(&[(122, 1), (25, 1), (1234567, 100000)], 'W'),
+            Some(-122.42009601944444)
+        );
+        // Decimal minutes, zero seconds (many Canon/Nikon GPS units)
+        assert_eq!(degrees(&[(51, 1), (3045, 100), (0, 1)], 'N'), Some(51.5075));
+        // Decimal degrees, unused components written as 0/0
+        assert_eq!(
+            degrees(&[(4550566, 100000), (0, 0), (0, 0)], 'N'),
+            Some(45.50566)
+        );
+        // Fewer than three components
+        assert_eq!(degrees(&[(3387, 100)], 'S'), Some(-33.87));
+        assert_eq!(degrees(&[(33, 1), (52, 1)], 'S'), Some(-33.86666666666667));
+        // Broken values
+        assert_eq!(degrees(&[(45, 0), (30, 1), (0, 1)], 'N'), None);
+        assert_eq!(degrees(&[], 'N'), None);
+        assert_eq!(degrees(&[(45, 1), (30, 1), (0, 1)], 'X'), None);
+        assert_eq!(degrees(&[(45, 1), (30, 1), (0, 1)], 'E'), Some(45.5));
+    }
+
+    #[test]
+    fn test_from_exif_without_position() {
+        let fields = vec![rational_field(Tag::GPSAltitude, &[(4679, 100)])];
+        assert!(
+            FileLocation::from_exif("x.jpg", &exif_from_fields(&fields), &ReadOptions::default())
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_from_exif_altitude_and_direction_refs() {
+        let mut fields = gps_fields();
+        fields.push(rational_field(Tag::GPSAltitude, &[(430, 1)]));
+        fields.push(exif::Field {
+            tag: Tag::GPSAltitudeRef,
+            ifd_num: In::PRIMARY,
+            value: Value::Byte(vec![1]),
+        });
+        fields.push(rational_field(Tag::GPSImgDirection, &[(2705, 10)]));
+        fields.push(ascii_field(Tag::GPSImgDirectionRef, "M"));
+        let fl =
+            FileLocation::from_exif("x.jpg", &exif_from_fields(&fields), &ReadOptions::default())
+                .unwrap();
+        assert_eq!(fl.altitude, Some(-430.0));
+        assert_eq!(fl.direction, Some(270.5));
+        assert_eq!(fl.direction_ref, Some(DirectionRef::Magnetic));
+    }
+
+    #[test]
+    fn test_from_exif_utc_from_gps() {
+        let mut fields = gps_fields();
+        fields.push(ascii_field(Tag::DateTimeOriginal, "2025:03:06 05:41:42"));
+        fields.push(ascii_field(Tag::GPSDateStamp, "2025:03:06"));
+        fields.push(rational_field(
+            Tag::GPSTimeStamp,
+            &[(4, 1), (41, 1), (4150, 100)],
+        ));
+        let fl =
+            FileLocation::from_exif("x.jpg", &exif_from_fields(&fields), &ReadOptions::default())
+                .unwrap();
+        assert_eq!(fl.timestamp_offset, None);
+        assert_eq!(
+            fl.timestamp_utc,
+            Some("2025-03-06T04:41:41.500Z".to_string())
+        );
+        assert_eq!(fl.timestamp_utc_source, Some(UtcSource::Gps));
+    }
+
+    /// Capture times in other formats or padded are read, and `DateTimeDigitized` or
+    /// `DateTime` are used without `DateTimeOriginal`.
+    #[test]
+    fn test_from_exif_capture_time_fallbacks() {
+        let read = |extra: Vec<exif::Field>| {
+            let mut fields = gps_fields();
+            fields.extend(extra);
+            FileLocation::from_exif("x.jpg", &exif_from_fields(&fields), &ReadOptions::default())
+                .unwrap()
+        };
+        let fl = read(vec![
+            ascii_field(Tag::DateTimeOriginal, "0000:00:00 00:00:00"),
+            ascii_field(Tag::DateTimeDigitized, "2025-03-06 05:41:42\0"),
+            ascii_field(Tag::OffsetTimeDigitized, "+01:00"),
+            ascii_field(Tag::DateTime, "2025:04:01 12:00:00"),
+        ]);
+        assert_eq!(fl.timestamp, Some("2025:03:06 05:41:42".to_string()));
+        assert_eq!(fl.timestamp_source, Some(TimestampSource::Digitized));
+        assert_eq!(fl.timestamp_utc, Some("2025-03-06T04:41:42Z".to_string()));
+        assert_eq!(fl.timestamp_utc_source, Some(UtcSource::Offset));
+
+        let fl = read(vec![ascii_field(Tag::DateTime, "2025:04:01 12:00:00")]);
+        assert_eq!(fl.timestamp_source, Some(TimestampSource::Modified));
+
+        let fl = read(vec![
+            ascii_field(Tag::DateTimeOriginal, "    :  :     :  :  "),
+            ascii_field(Tag::GPSDateStamp, "2025:03:06"),
+            rational_field(Tag::GPSTimeStamp, &[(4, 1), (41, 1), (36, 1)]),
+        ]);
+        assert_eq!(fl.timestamp, None);
+        assert_eq!(fl.timestamp_source, Some(TimestampSource::Gps));
+        assert_eq!(fl.timestamp_utc_source, Some(UtcSource::Gps));
+    }
+
+    /// A file without metadata, placed by an XMP sidecar without a capture time.
+    #[test]
+    fn test_from_file_fallback_time() {
+        let dir = std::env::temp_dir().join("img_coords_test_fallback_time");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir(&dir).unwrap();
+        let read = |name: &str| {
+            let path = dir.join(name);
+            std::fs::copy("test_files/sunrise_takeout.jpg", &path).unwrap();
+            std::fs::write(
+                dir.join(format!("{name}.xmp")),
+                r#"<rdf:Description xmlns:exif="http://ns.adobe.com/exif/1.0/"
+                    exif:GPSLatitude="45,26.058N" exif:GPSLongitude="12,20.328E"/>"#,
+            )
+            .unwrap();
+            FileLocation::from_file(path.to_str().unwrap()).unwrap()
+        };
+        let fl = read("plain.jpg");
+        assert_eq!(fl.timestamp, None);
+        assert_eq!(fl.timestamp_source, Some(TimestampSource::FileTime));
+        assert_eq!(fl.timestamp_utc_source, Some(UtcSource::FileTime));
+        assert!(fl.capture_time().is_some());
+
+        let fl = read("PXL_20250306_054142123.jpg");
+        assert_eq!(fl.timestamp, Some("2025:03:06 05:41:42".to_string()));
+        assert_eq!(fl.timestamp_source, Some(TimestampSource::FileName));
+        assert_eq!(fl.timestamp_utc, None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_from_exif_object_location() {
+        let read = |extra: Vec<exif::Field>| {
+            let mut fields = gps_fields();
+            fields.extend(extra);
+            FileLocation::from_exif("x.jpg", &exif_from_fields(&fields), &ReadOptions::default())
+                .unwrap()
+                .object
+        };
+        assert_eq!(read(vec![]), None);
+
+        let object = read(vec![
+            ascii_field(Tag::GPSDestLatitudeRef, "N"),
+            rational_field(Tag::GPSDestLatitude, &[(45, 1), (30, 1), (2400, 100)]),
+            ascii_field(Tag::GPSDestLongitudeRef, "E"),
+            rational_field(Tag::GPSDestLongitude, &[(12, 1), (20, 1), (3000, 100)]),
+            rational_field(Tag::GPSDestBearing, &[(45, 1)]),
+            ascii_field(Tag::GPSDestBearingRef, "T"),
+            rational_field(Tag::GPSDestDistance, &[(15, 100)]),
+        ])
+        .unwrap();
+        assert_eq!(object.latitude, 45.50666666666667);
+        assert_eq!(object.longitude, 12.341666666666667);
+        assert_eq!(object.bearing, Some(45.0));
+        assert_eq!(object.bearing_ref, Some(DirectionRef::True));
+        assert_eq!(object.distance, Some(150.0)); // Kilometres by default
+
+        // Without a position, from the bearing and distance
+        let object = read(vec![
+            rational_field(Tag::GPSDestBearing, &[(0, 1)]),
+            rational_field(Tag::GPSDestDistance, &[(1, 1)]),
+            ascii_field(Tag::GPSDestDistanceRef, "N"),
+        ])
+        .unwrap();
+        assert_eq!(object.distance, Some(1852.0));
+        assert!((object.latitude - (45.50566666666667 + 1.0 / 60.0)).abs() < 1e-4);
+        assert!((object.longitude - 12.341161111111111).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_from_exif_speed_and_travel_direction() {
+        let read = |extra: Vec<exif::Field>| {
+            let mut fields = gps_fields();
+            fields.extend(extra);
+            FileLocation::from_exif("x.jpg", &exif_from_fields(&fields), &ReadOptions::default())
+                .unwrap()
+        };
+        let fl = read(vec![]);
+        assert_eq!((fl.speed, fl.travel_direction), (None, None));
+
+        let fl = read(vec![
+            rational_field(Tag::GPSSpeed, &[(36, 1)]), // km/h without GPSSpeedRef
+            rational_field(Tag::GPSTrack, &[(18250, 100)]),
+            ascii_field(Tag::GPSTrackRef, "M"),
+        ]);
+        assert_eq!(fl.speed, Some(10.0));
+        assert_eq!(fl.travel_direction, Some(182.5));
+        assert_eq!(fl.travel_direction_ref, Some(DirectionRef::Magnetic));
+        let kml = fl.as_kml();
+        assert!(kml.contains(r#"<Data name="speed"><value>10</value></Data>"#));
+        assert!(kml.contains(r#"<Data name="travel_direction_ref"><value>M</value></Data>"#));
+
+        for (unit, expected) in [("K", 10.0), ("M", 16.09344), ("N", 18.52)] {
+            let fl = read(vec![
+                rational_field(Tag::GPSSpeed, &[(36, 1)]),
+                ascii_field(Tag::GPSSpeedRef, unit),
+            ]);
+            assert!((fl.speed.unwrap() - expected).abs() < 1e-9, "{unit}");
+        }
+    }
+
+    #[test]
+    fn test_from_exif_map_datum() {
+        let read = |datum: &str| {
+            let mut fields = gps_fields();
+            fields.push(ascii_field(Tag::GPSMapDatum, datum));
+            fields.push(rational_field(Tag::GPSDestBearing, &[(90, 1)]));
+            fields.push(rational_field(Tag::GPSDestDistance, &[(1, 10)]));
+            FileLocation::from_exif("x.jpg", &exif_from_fields(&fields), &ReadOptions::default())
+                .unwrap()
+        };
+        let wgs84 = read("WGS-84");
+        assert_eq!(wgs84.map_datum, None);
+        assert!((wgs84.latitude - 45.50566666666667).abs() < 1e-9);
+
+        // ED50 is about 100 m south-west of WGS 84 here; the object moves with it
+        let ed50 = read("ED50");
+        assert_eq!(ed50.map_datum.as_deref(), Some("ED50"));
+        assert!((ed50.latitude - wgs84.latitude + 0.0009).abs() < 1e-4);
+        assert!((ed50.longitude - wgs84.longitude + 0.001).abs() < 1e-4);
+        assert!(ed50.as_geojson().contains(r#""map_datum":"ED50""#));
+        let (wgs84_object, ed50_object) = (wgs84.object.unwrap(), ed50.object.unwrap());
+        assert!((ed50_object.longitude - wgs84_object.longitude + 0.001).abs() < 1e-4);
+
+        // Unknown datums are kept as they are
+        let unknown = read("PULKOVO 1942");
+        assert_eq!(unknown.map_datum.as_deref(), Some("PULKOVO 1942"));
+        assert_eq!(unknown.latitude, wgs84.latitude);
+    }
+
+    #[test]
+    fn test_from_exif_offset_beats_gps() {
+        let mut fields = gps_fields();
+        fields.push(ascii_field(Tag::DateTimeOriginal, "2025:03:06 23:30:00"));
+        fields.push(ascii_field(Tag::OffsetTimeOriginal, "-05:00"));
+        fields.push(ascii_field(Tag::SubSecTimeOriginal, "5"));
+        fields.push(ascii_field(Tag::GPSDateStamp, "2025:03:07"));
+        fields.push(rational_field(
+            Tag::GPSTimeStamp,
+            &[(4, 1), (29, 1), (59, 1)],
+        ));
+        let fl =
+            FileLocation::from_exif("x.jpg", &exif_from_fields(&fields), &ReadOptions::default())
+                .unwrap();
+        assert_eq!(
+            fl.timestamp_utc,
+            Some("2025-03-07T04:30:00.500Z".to_string())
+        );
+        assert_eq!(fl.timestamp_utc_source, Some(UtcSource::Offset));
+    }
+
+    #[test]
+    fn test_from_exif_accuracy() {
+        let mut fields = gps_fields();
+        fields.push(rational_field(Tag::GPSHPositioningError, &[(35, 10)]));
+        fields.push(rational_field(Tag::GPSDOP, &[(12, 10)]));
+        fields.push(ascii_field(Tag::GPSMeasureMode, "3"));
+        fields.push(ascii_field(Tag::GPSSatellites, "09"));
+        fields.push(ascii_field(Tag::GPSStatus, "A"));
+        let fl =
+            FileLocation::from_exif("x.jpg", &exif_from_fields(&fields), &ReadOptions::default())
+                .unwrap();
+        let accuracy = fl.accuracy.as_ref().unwrap();
+        assert_eq!(accuracy.h_positioning_error, Some(3.5));
+        assert_eq!(accuracy.dop, Some(1.2));
+        assert_eq!(accuracy.measure_mode, Some(3));
+        assert_eq!(accuracy.satellites, Some("09".to_string()));
+        assert_eq!(accuracy.status, Some("A".to_string()));
+        assert_eq!(fl.estimated_error(), Some(3.5));
+    }
+
+    #[test]
+    fn test_capture_time_prefers_utc() {
+        let mut fl = FileLocation {
+            timestamp: Some("2025:03:06 23:30:00".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            fl.capture_time().unwrap().to_string(),
+            "2025-03-06 23:30:00"
+        );
+        fl.timestamp_utc = Some("2025-03-07T04:30:00.500Z".to_string());
+        assert_eq!(
+            fl.capture_time().unwrap().to_string(),
+            "2025-03-07 04:30:00.500"
+        );
+    }
+
+    #[test]
+    fn test_kml_roundtrip_direction() {
+        let fl = FileLocation {
+            file: "x.jpg".to_string(),
+            latitude: 1.0,
+            longitude: 2.0,
+            direction: Some(270.5),
+            direction_ref: Some(DirectionRef::True),
+            pitch: Some(-90.0),
+            relative_altitude: Some(30.1),
+            ..Default::default()
+        };
+        let kml = Kml::from_str(&fl.as_kml()).unwrap();
+        let back = FileLocation::from_kml_element(&kml).unwrap();
+        assert_eq!(back.direction, Some(270.5));
+        assert_eq!(back.direction_ref, Some(DirectionRef::True));
+        assert_eq!(back.pitch, Some(-90.0));
+        assert_eq!(back.relative_altitude, Some(30.1));
+    }
+
+    #[test]
+    fn test_kml_roundtrip_camera() {
+        let camera = CameraInfo {
+            make: Some("A & B".to_string()),
+            iso: Some(400),
+            exposure_time: Some(0.004),
+            ..Default::default()
+        };
+        let fl = FileLocation {
+            file: "x.jpg".to_string(),
+            camera: Some(camera.clone()),
+            ..Default::default()
+        };
+        let kml = Kml::from_str(&fl.as_kml()).unwrap();
+        let back = FileLocation::from_kml_element(&kml).unwrap();
+        assert_eq!(back.camera, Some(camera));
+    }
+
+    #[test]
+    fn test_kml_roundtrip_description() {
+        let description = Description {
+            title: Some("Sunrise & fog".to_string()),
+            keywords: vec!["sunrise".to_string(), "fog".to_string()],
+            city: Some("Venice".to_string()),
+            ..Default::default()
+        };
+        let fl = FileLocation {
+            file: "x.jpg".to_string(),
+            description: Some(description.clone()),
+            ..Default::default()
+        };
+        let kml = fl.as_kml();
+        assert!(kml.contains("<name>Sunrise &amp; fog</name>"));
+        let back = FileLocation::from_kml_element(&Kml::from_str(&kml).unwrap()).unwrap();
+        assert_eq!(back.file, "x.jpg");
+        assert_eq!(back.description, Some(description));
     }
 
     #[test]
@@ -302,9 +2305,10 @@ mod tests {
             direction: Some(11.0),
             thumbnail: None,
             timestamp: Some("2025:03:06 05:41:42".to_string()),
+            ..Default::default()
         };
         let kml = fl.as_kml();
-        assert_eq!(kml, "<Placemark><name>test_files/sunrise.jpg</name><Point><coordinates>12.345,45.6789,46.79</coordinates></Point><TimeStamp><when>2025:03:06T05:41:42</when></TimeStamp></Placemark>");
+        assert_eq!(kml, "<Placemark><name>test_files/sunrise.jpg</name><Point><coordinates>12.345,45.6789,46.79</coordinates></Point><TimeStamp><when>2025:03:06T05:41:42</when></TimeStamp><ExtendedData><Data name=\"direction\"><value>11</value></Data></ExtendedData></Placemark>");
     }
 
     #[test]
@@ -317,25 +2321,78 @@ mod tests {
             direction: Some(11.0),
             thumbnail: Some("base64".to_string()),
             timestamp: Some("2025:03:06 05:41:42".to_string()),
+            ..Default::default()
         };
         let geojson = fl.as_geojson();
         let expected = r#"{"geometry":{"coordinates":[12.345,45.6789],"type":"Point"},"properties":{"altitude":46.79,"direction":11.0,"name":"test_files/sunrise.jpg","thumbnail":"base64","timestamp":"2025:03:06 05:41:42"},"type":"Feature"}"#;
         assert_eq!(geojson, expected);
     }
 
-    /// `as_geojson` -> `from_geojson_feature` must reproduce every field. This guards
-    /// the update workflow: emit GeoJSON, then re-load it with `--update`.
-    #[test]
-    fn test_geojson_roundtrip_full() {
-        let fl = FileLocation {
+    /// A location with every field set, for the round-trip tests.
+    fn full_location() -> FileLocation {
+        FileLocation {
             file: "a/b c.jpg".to_string(),
             latitude: 45.6789,
             longitude: 12.345,
             altitude: Some(46.79),
             direction: Some(11.0),
+            direction_ref: Some(DirectionRef::True),
             thumbnail: Some("base64data".to_string()),
             timestamp: Some("2025:03:06 05:41:42".to_string()),
-        };
+            timestamp_offset: Some("+01:00".to_string()),
+            timestamp_utc: Some("2025-03-06T04:41:42.125Z".to_string()),
+            timestamp_utc_source: Some(UtcSource::Offset),
+            timestamp_gps: Some("2025-03-06T04:41:36Z".to_string()),
+            missing_tags: vec!["GPSImgDirection".to_string()],
+            camera: Some(CameraInfo {
+                model: Some("Pixel 8 Pro".to_string()),
+                f_number: Some(2.8),
+                ..Default::default()
+            }),
+            accuracy: Some(GpsAccuracy {
+                h_positioning_error: Some(4.2),
+                status: Some("A".to_string()),
+                ..Default::default()
+            }),
+            low_accuracy: true,
+            validity: Validity::Suspicious("stale".to_string()),
+            location_source: Some(LocationSource::XmpSidecar),
+            media_type: MediaType::Video,
+            track: None, // A separate feature, see `FileSet`
+            pitch: Some(-45.0),
+            relative_altitude: Some(30.1),
+            description: Some(Description {
+                title: Some("Sunrise".to_string()),
+                caption: Some("Over the lagoon".to_string()),
+                keywords: vec!["sunrise".to_string()],
+                sublocation: Some("Riva degli Schiavoni".to_string()),
+                city: Some("Venice".to_string()),
+                state: Some("Veneto".to_string()),
+                country: Some("Italy".to_string()),
+                rating: Some(5),
+                label: Some("Green".to_string()),
+            }),
+            mime_type: Some("image/heif".to_string()),
+            timestamp_source: Some(TimestampSource::Digitized),
+            object: Some(ObjectLocation {
+                latitude: 45.6791,
+                longitude: 12.3462,
+                bearing: Some(45.0),
+                bearing_ref: Some(DirectionRef::True),
+                distance: Some(120.5),
+            }),
+            speed: Some(13.9),
+            travel_direction: Some(182.5),
+            travel_direction_ref: Some(DirectionRef::Magnetic),
+            map_datum: Some("TOKYO".to_string()),
+        }
+    }
+
+    /// `as_geojson` -> `from_geojson_feature` must reproduce every field. This guards
+    /// the update workflow: emit GeoJSON, then re-load it with `--update`.
+    #[test]
+    fn test_geojson_roundtrip_full() {
+        let fl = full_location();
         let v: serde_json::Value = serde_json::from_str(&fl.as_geojson()).unwrap();
         let back = FileLocation::from_geojson_feature(&v).unwrap();
         assert_eq!(back.file, fl.file);
@@ -343,8 +2400,85 @@ mod tests {
         assert_eq!(back.longitude, fl.longitude);
         assert_eq!(back.altitude, fl.altitude);
         assert_eq!(back.direction, fl.direction);
+        assert_eq!(back.direction_ref, fl.direction_ref);
         assert_eq!(back.thumbnail, fl.thumbnail);
         assert_eq!(back.timestamp, fl.timestamp);
+        assert_eq!(back.timestamp_offset, fl.timestamp_offset);
+        assert_eq!(back.timestamp_utc, fl.timestamp_utc);
+        assert_eq!(back.timestamp_utc_source, fl.timestamp_utc_source);
+        assert_eq!(back.missing_tags, fl.missing_tags);
+        assert_eq!(back.camera, fl.camera);
+        assert_eq!(back.accuracy, fl.accuracy);
+        assert_eq!(back.low_accuracy, fl.low_accuracy);
+        assert_eq!(back.validity, fl.validity);
+        assert_eq!(back.timestamp_gps, fl.timestamp_gps);
+        assert_eq!(back.location_source, fl.location_source);
+        assert_eq!(back.media_type, fl.media_type);
+        assert_eq!(back.pitch, fl.pitch);
+        assert_eq!(back.relative_altitude, fl.relative_altitude);
+        assert_eq!(back.description, fl.description);
+        assert_eq!(back.mime_type, fl.mime_type);
+        assert_eq!(back.timestamp_source, fl.timestamp_source);
+        assert_eq!(back.object, fl.object);
+        assert_eq!(back.speed, fl.speed);
+        assert_eq!(back.travel_direction, fl.travel_direction);
+        assert_eq!(back.travel_direction_ref, fl.travel_direction_ref);
+        assert_eq!(back.map_datum, fl.map_datum);
+    }
+
+    /// `as_kml` -> `from_kml_element` must reproduce every field KML can hold, which is
+    /// all of them but the thumbnail.
+    #[test]
+    fn test_kml_roundtrip_full() {
+        let fl = full_location();
+        let kml: Kml = fl.as_kml().parse().unwrap();
+        let back = FileLocation::from_kml_element(&kml).unwrap();
+        assert_eq!(back.file, fl.file);
+        assert_eq!(back.latitude, fl.latitude);
+        assert_eq!(back.longitude, fl.longitude);
+        assert_eq!(back.altitude, fl.altitude);
+        assert_eq!(back.direction, fl.direction);
+        assert_eq!(back.direction_ref, fl.direction_ref);
+        assert_eq!(back.thumbnail, None);
+        assert_eq!(back.timestamp, fl.timestamp);
+        assert_eq!(back.timestamp_offset, fl.timestamp_offset);
+        assert_eq!(back.timestamp_utc, fl.timestamp_utc);
+        assert_eq!(back.timestamp_utc_source, fl.timestamp_utc_source);
+        assert_eq!(back.missing_tags, fl.missing_tags);
+        assert_eq!(back.camera, fl.camera);
+        assert_eq!(back.accuracy, fl.accuracy);
+        assert_eq!(back.low_accuracy, fl.low_accuracy);
+        assert_eq!(back.validity, fl.validity);
+        assert_eq!(back.timestamp_gps, fl.timestamp_gps);
+        assert_eq!(back.location_source, fl.location_source);
+        assert_eq!(back.media_type, fl.media_type);
+        assert_eq!(back.pitch, fl.pitch);
+        assert_eq!(back.relative_altitude, fl.relative_altitude);
+        assert_eq!(back.description, fl.description);
+        assert_eq!(back.mime_type, fl.mime_type);
+        assert_eq!(back.timestamp_source, fl.timestamp_source);
+        assert_eq!(back.object, fl.object);
+        assert_eq!(back.speed, fl.speed);
+        assert_eq!(back.travel_direction, fl.travel_direction);
+        assert_eq!(back.travel_direction_ref, fl.travel_direction_ref);
+        assert_eq!(back.map_datum, fl.map_datum);
+    }
+
+    /// Without a UTC time, `<TimeStamp>` holds the local one.
+    #[test]
+    fn test_kml_roundtrip_local_time() {
+        let fl = FileLocation {
+            file: "a.jpg".to_string(),
+            latitude: 45.6789,
+            longitude: 12.345,
+            timestamp: Some("2025:03:06 05:41:42".to_string()),
+            ..Default::default()
+        };
+        let kml: Kml = fl.as_kml().parse().unwrap();
+        let back = FileLocation::from_kml_element(&kml).unwrap();
+        assert_eq!(back.timestamp, fl.timestamp);
+        assert_eq!(back.timestamp_utc, None);
+        assert!(back.missing_tags.is_empty());
     }
 
     /// Same round-trip but for a location with no altitude/direction/thumbnail/timestamp
@@ -360,6 +2494,7 @@ mod tests {
             direction: None,
             thumbnail: None,
             timestamp: None,
+            ..Default::default()
         };
         let v: serde_json::Value = serde_json::from_str(&fl.as_geojson()).unwrap();
         let back = FileLocation::from_geojson_feature(&v)
@@ -403,10 +2538,14 @@ mod tests {
             direction: None,
             thumbnail: None,
             timestamp: Some("2025:03:06 05:41:42".to_string()),
+            ..Default::default()
         };
         let parsed = fl.timestamp_parsed().unwrap();
         assert_eq!(parsed.to_string(), "2025-03-06 05:41:42");
 
+        fl.timestamp = Some("2025-03-06T05:41:42".to_string());
+        assert_eq!(fl.timestamp_parsed(), Some(parsed));
+
         fl.timestamp = Some("not a date".to_string());
         assert!(fl.timestamp_parsed().is_none());
 
@@ -426,6 +2565,7 @@ mod tests {
             direction: None,
             thumbnail: None,
             timestamp: None,
+            ..Default::default()
         };
         let kml = fl.as_kml();
         assert!(kml.contains("<name>a &amp; b &lt;tag&gt; &quot;q&quot; &apos;x&apos;.jpg</name>"));
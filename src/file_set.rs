@@ -1,27 +1,45 @@
-use crate::file_location::FileLocation;
+use crate::file_location::{FileLocation, ReadOptions, SidecarMode};
+use crate::file_type::{FileType, OpenedFile};
+use crate::lightroom;
+use crate::object_location::ObjectLocation;
+use crate::track::Track;
+use crate::validation;
 use anyhow::{Context, Result, anyhow, bail};
 use chrono::NaiveDateTime;
 use jwalk::WalkDir;
 use jwalk::rayon::prelude::*;
 use kml::Kml;
-use regex::{Regex, RegexBuilder};
 use std::io::{self, BufRead, Write};
 use std::path::Path;
-use std::sync::LazyLock;
-use std::{collections::HashSet, fs};
-
-static RE_VALID_FILE_TYPE: LazyLock<Regex> = LazyLock::new(|| {
-    RegexBuilder::new(r"\.(png|gif|tif|tiff|jpg|jpeg)$")
-        .case_insensitive(true)
-        .build()
-        .expect("re_valid_file_type does not compile")
-});
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+/// Extensions of the files that are read, lower case. GIF is not among them, as it has
+/// no place for a position.
+const DEFAULT_EXTENSIONS: &[&str] = &[
+    "png", "tif", "tiff", "jpg", "jpeg", "jpe", "jfif", "webp", "heic", "heif", "hif", "avif",
+    "dng", "cr2", "cr3", "nef", "arw", "orf", "rw2", "raf", "mov", "mp4", "m4v", "srt",
+];
 
 #[derive(Clone, Debug, Default)]
 pub struct FileSet {
     file_locations: Vec<FileLocation>,
     before: Option<NaiveDateTime>,
     after: Option<NaiveDateTime>,
+    read_options: ReadOptions,
+    max_error: Option<f64>,
+    flag_inaccurate: bool,
+    keep_invalid: bool,
+    quarantine: Vec<FileLocation>,
+    min_rating: Option<i32>,
+    labels: Vec<String>,
+    include_keywords: Vec<String>,
+    exclude_keywords: Vec<String>,
+    extra_extensions: Vec<String>,
+    excluded_extensions: Vec<String>,
+    sight_lines: bool,
 }
 
 impl FileSet {
@@ -33,6 +51,67 @@ impl FileSet {
         self.after = Some(date);
     }
 
+    /// Keeps only locations rated at least `stars` (XMP `xmp:Rating`); unrated ones are
+    /// dropped.
+    pub fn set_min_rating(&mut self, stars: i32) {
+        self.min_rating = Some(stars);
+    }
+
+    /// Keeps only locations with one of these color labels (XMP `xmp:Label`), ignoring
+    /// case. An empty list keeps all.
+    pub fn set_labels(&mut self, labels: Vec<String>) {
+        self.labels = labels;
+    }
+
+    /// Keeps only locations with at least one of the `include` keywords (if any are
+    /// given), and none of the `exclude` ones, ignoring case.
+    pub fn set_keywords(&mut self, include: Vec<String>, exclude: Vec<String>) {
+        self.include_keywords = include;
+        self.exclude_keywords = exclude;
+    }
+
+    /// Reads files with the `extra` extensions as well, and not those with the
+    /// `excluded` ones. A leading dot is ignored, as is case.
+    pub fn set_extensions(&mut self, extra: Vec<String>, excluded: Vec<String>) {
+        let normalize = |extensions: Vec<String>| -> Vec<String> {
+            extensions
+                .iter()
+                .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+                .filter(|e| !e.is_empty())
+                .collect()
+        };
+        self.extra_extensions = normalize(extra);
+        self.excluded_extensions = normalize(excluded);
+    }
+
+    /// Writes the object position of images that have one as an extra feature/placemark,
+    /// with the line of sight from the camera.
+    pub fn set_sight_lines(&mut self, sight_lines: bool) {
+        self.sight_lines = sight_lines;
+    }
+
+    pub fn set_exif_fields(&mut self, exif_fields: bool) {
+        self.read_options.exif_fields = exif_fields;
+    }
+
+    pub fn set_sidecar_mode(&mut self, mode: SidecarMode) {
+        self.read_options.sidecars = mode;
+    }
+
+    /// Locations whose estimated error exceeds `metres` are dropped, or only flagged
+    /// (`FileLocation::low_accuracy`) if `flag_only` is set. Locations without any
+    /// accuracy information are kept as they are.
+    pub fn set_max_error(&mut self, metres: f64, flag_only: bool) {
+        self.max_error = Some(metres);
+        self.flag_inaccurate = flag_only;
+    }
+
+    /// Keeps locations that fail validation in the output, instead of only in the
+    /// quarantine report.
+    pub fn set_keep_invalid(&mut self, keep_invalid: bool) {
+        self.keep_invalid = keep_invalid;
+    }
+
     pub fn load_from_file(&mut self, path: &str) -> Result<()> {
         let data =
             fs::read_to_string(path).with_context(|| format!("Failed to read file '{path}'"))?;
@@ -54,32 +133,40 @@ impl FileSet {
     fn load_from_kml(&mut self, data: &str) -> Result<()> {
         let kml: Kml = data.parse().context("not valid KML")?;
         let mut locations = Vec::new();
-        Self::collect_placemarks(&kml, &mut locations);
+        let mut tracks = Vec::new();
+        Self::collect_placemarks(&kml, &mut locations, &mut tracks);
         if locations.is_empty() {
             bail!("no placemarks with coordinates found");
         }
         self.file_locations = locations;
+        self.attach_tracks(tracks);
         Ok(())
     }
 
     /// Placemarks in a KML file are typically wrapped in `<Document>`/`<Folder>`
     /// containers (this is how the crate parses them, and how our own KML output is
     /// shaped), so walk the tree rather than only inspecting the top-level elements.
-    fn collect_placemarks(element: &Kml, out: &mut Vec<FileLocation>) {
+    fn collect_placemarks(
+        element: &Kml,
+        out: &mut Vec<FileLocation>,
+        tracks: &mut Vec<(String, Track)>,
+    ) {
         match element {
             Kml::KmlDocument(doc) => {
                 for e in &doc.elements {
-                    Self::collect_placemarks(e, out);
+                    Self::collect_placemarks(e, out, tracks);
                 }
             }
             Kml::Document { elements, .. } | Kml::Folder { elements, .. } => {
                 for e in elements {
-                    Self::collect_placemarks(e, out);
+                    Self::collect_placemarks(e, out, tracks);
                 }
             }
-            Kml::Placemark(_) => {
+            Kml::Placemark(pm) => {
                 if let Some(fl) = FileLocation::from_kml_element(element) {
                     out.push(fl);
+                } else if let Some(track) = Track::from_kml_placemark(pm) {
+                    tracks.push(track);
                 }
             }
             _ => {}
@@ -97,9 +184,23 @@ impl FileSet {
             .par_iter()
             .filter_map(FileLocation::from_geojson_feature)
             .collect();
+        let tracks = features
+            .par_iter()
+            .filter_map(Track::from_geojson_feature)
+            .collect();
+        self.attach_tracks(tracks);
         Ok(())
     }
 
+    /// Video tracks are stored as separate LineString features with the same name as
+    /// the video's point.
+    fn attach_tracks(&mut self, tracks: Vec<(String, Track)>) {
+        let mut tracks: HashMap<String, Track> = tracks.into_iter().collect();
+        for fl in &mut self.file_locations {
+            fl.track = tracks.remove(&fl.file);
+        }
+    }
+
     pub fn scan_tree(&mut self, root: &str) -> Result<()> {
         // jwalk defers access errors to iteration (where they'd be silently dropped by
         // `f.ok()`), so check the root up front to give a clear message for a bad path.
@@ -110,20 +211,23 @@ impl FileSet {
         }
 
         // The directory walk itself is parallelized internally by jwalk. We collect
-        // the raw paths first, then fan out the expensive per-file work (extension
-        // filtering + `canonicalize` syscall) across the rayon thread pool. Filtering
-        // by extension *before* canonicalizing avoids a syscall for every non-image.
+        // the paths of files (and of links, which may point to one) first, then fan
+        // out the expensive per-file work (extension filtering + `canonicalize`
+        // syscall) across the rayon thread pool. Filtering by extension *before*
+        // canonicalizing avoids a syscall for every file of another type; only those
+        // without an extension are canonicalized and then sniffed in `add_files`.
         let paths: Vec<std::path::PathBuf> = WalkDir::new(root)
             // .follow_links(true)
             .try_into_iter()
             .with_context(|| format!("Failed to scan directory tree at '{root}'"))?
             .filter_map(|f| f.ok())
+            .filter(|f| f.file_type().is_file() || f.file_type().is_symlink())
             .map(|f| f.path())
             .collect();
 
         let file_candidates = paths
             .into_par_iter()
-            .filter(|p| Self::has_valid_extension(p))
+            .filter(|p| self.may_be_candidate(p))
             .filter_map(|p| p.canonicalize().ok())
             .filter_map(|p| p.to_str().map(|p| p.to_string()))
             .collect();
@@ -131,9 +235,33 @@ impl FileSet {
         Ok(())
     }
 
-    fn has_valid_extension(path: &Path) -> bool {
-        path.to_str()
-            .is_some_and(|s| RE_VALID_FILE_TYPE.is_match(s))
+    fn has_valid_extension(&self, extension: &str) -> bool {
+        let extension = extension.to_lowercase();
+        !self.excluded_extensions.contains(&extension)
+            && (DEFAULT_EXTENSIONS.contains(&extension.as_str())
+                || self.extra_extensions.contains(&extension))
+    }
+
+    /// Files with a valid extension, and those without one, which camera apps sometimes
+    /// write; the cheap check before the file is opened to sniff its type.
+    fn may_be_candidate(&self, path: &Path) -> bool {
+        match path.extension() {
+            Some(extension) => extension
+                .to_str()
+                .is_some_and(|e| self.has_valid_extension(e)),
+            None => true,
+        }
+    }
+
+    /// Decides by the type sniffed from the first bytes of the file whether to read it.
+    /// Files without an extension must be of a known type; files with one are read
+    /// unless they are of a type without positions, whatever their extension says.
+    fn is_candidate(path: &Path, file_type: Option<FileType>) -> bool {
+        match (file_type, path.extension()) {
+            (Some(file_type), _) => file_type.may_have_location(),
+            (None, Some(_)) => true, // Eg DJI SRT logs, which are text
+            (None, None) => false,
+        }
     }
 
     pub fn import_files(&mut self) {
@@ -145,33 +273,163 @@ impl FileSet {
         self.add_files(file_candidates);
     }
 
-    fn add_files(&mut self, file_candidates: Vec<String>) {
-        let existing: HashSet<String> = self
-            .file_locations
+    /// Reads the JSON array `exiftool -j -n` writes, for files exiftool can read but
+    /// this crate cannot. Files already in the set are skipped, as with `add_files`.
+    pub fn import_exiftool_json(&mut self, data: &str) -> Result<()> {
+        let res: serde_json::Value = serde_json::from_str(data).context("not valid JSON")?;
+        let entries = res.as_array().context("not a JSON array")?;
+        let existing = self.existing_files();
+        let new_file_locations = entries
             .par_iter()
-            .map(|fl| fl.file.to_owned())
+            .filter_map(|v| FileLocation::from_exiftool_entry(v, &self.read_options))
+            .filter(|fl| !existing.contains(&fl.file))
+            .collect();
+        self.add_locations(new_file_locations);
+        Ok(())
+    }
+
+    /// Adds the geotagged images of a Lightroom Classic catalog that are not in the set
+    /// yet, so positions that only exist in the catalog are not lost after a scan.
+    pub fn load_lightroom_catalog(&mut self, path: &str) -> Result<()> {
+        let existing = self.existing_files();
+        let new_file_locations = lightroom::images_from_catalog(path)?
+            .into_iter()
+            .filter(|image| !existing.contains(&image.file))
+            .map(FileLocation::from_lightroom)
             .collect();
-        let mut new_file_locations: Vec<FileLocation> = file_candidates
+        self.add_locations(new_file_locations);
+        Ok(())
+    }
+
+    fn existing_files(&self) -> HashSet<String> {
+        self.file_locations
+            .par_iter()
+            .map(|fl| fl.file.to_owned())
+            .collect()
+    }
+
+    fn add_files(&mut self, file_candidates: Vec<String>) {
+        let existing = self.existing_files();
+        let new_file_locations = file_candidates
             .par_iter()
             .filter(|f| !existing.contains(*f)) // Not already in set
-            .filter(|f| RE_VALID_FILE_TYPE.is_match(f)) // Wrong file ending
-            .filter_map(|f| FileLocation::from_file(f))
+            .filter(|f| self.may_be_candidate(Path::new(f))) // Wrong extension
+            .filter_map(|f| {
+                let mut file = OpenedFile::open(f)?;
+                Self::is_candidate(Path::new(f), file.file_type)
+                    .then(|| FileLocation::from_opened_file(f, &mut file, &self.read_options))?
+            })
             .collect();
+        self.add_locations(new_file_locations);
+    }
+
+    /// Applies the date, description, accuracy and validation filters to new locations,
+    /// then adds them to the set.
+    fn add_locations(&mut self, mut new_file_locations: Vec<FileLocation>) {
         if let Some(before) = self.before {
-            new_file_locations.retain(|fl| match fl.timestamp_parsed() {
+            new_file_locations.retain(|fl| match fl.capture_time() {
                 Some(parsed) => parsed <= before,
                 None => false,
             });
         }
         if let Some(after) = self.after {
-            new_file_locations.retain(|fl| match fl.timestamp_parsed() {
+            new_file_locations.retain(|fl| match fl.capture_time() {
                 Some(date) => date >= after,
                 None => false,
             });
         }
+        new_file_locations.retain(|fl| self.matches_description(fl));
+        self.apply_max_error(&mut new_file_locations);
+        self.apply_validation(&mut new_file_locations);
         self.file_locations.append(&mut new_file_locations);
     }
 
+    /// Classifies each location, and moves invalid ones to the quarantine list unless
+    /// they are to be kept. Non-finite coordinates cannot be written as GeoJSON, so those
+    /// are never kept.
+    fn apply_validation(&mut self, file_locations: &mut Vec<FileLocation>) {
+        file_locations
+            .par_iter_mut()
+            .for_each(|fl| fl.validity = validation::validate(fl));
+        let mut kept = Vec::with_capacity(file_locations.len());
+        for fl in file_locations.drain(..) {
+            if !fl.validity.is_invalid() {
+                kept.push(fl);
+                continue;
+            }
+            let finite = fl.latitude.is_finite() && fl.longitude.is_finite();
+            if self.keep_invalid && finite {
+                kept.push(fl.clone());
+            }
+            self.quarantine.push(fl);
+        }
+        *file_locations = kept;
+    }
+
+    /// Writes the locations that failed validation, with the reason, as a JSON array.
+    pub fn write_quarantine_report(&self, path: &str) -> Result<()> {
+        let entries: Vec<serde_json::Value> = self
+            .quarantine
+            .iter()
+            .map(|fl| {
+                serde_json::json!({
+                    "name": fl.file,
+                    "latitude": fl.latitude,
+                    "longitude": fl.longitude,
+                    "reason": fl.validity.reason(),
+                })
+            })
+            .collect();
+        let data = serde_json::to_string_pretty(&entries)?;
+        fs::write(path, data).with_context(|| format!("Failed to write quarantine report '{path}'"))
+    }
+
+    /// The rating, label and keyword filters.
+    fn matches_description(&self, fl: &FileLocation) -> bool {
+        let description = fl.description.clone().unwrap_or_default();
+        if let Some(min_rating) = self.min_rating
+            && description.rating.is_none_or(|rating| rating < min_rating)
+        {
+            return false;
+        }
+        if !self.labels.is_empty()
+            && !description
+                .label
+                .as_ref()
+                .is_some_and(|label| self.labels.iter().any(|l| l.eq_ignore_ascii_case(label)))
+        {
+            return false;
+        }
+        if !self.include_keywords.is_empty()
+            && !self
+                .include_keywords
+                .iter()
+                .any(|k| description.has_keyword(k))
+        {
+            return false;
+        }
+        !self
+            .exclude_keywords
+            .iter()
+            .any(|k| description.has_keyword(k))
+    }
+
+    fn apply_max_error(&self, file_locations: &mut Vec<FileLocation>) {
+        let Some(max_error) = self.max_error else {
+            return;
+        };
+        let too_inaccurate =
+            |fl: &FileLocation| fl.estimated_error().is_some_and(|e| e > max_error);
+        if self.flag_inaccurate {
+            file_locations
+                .iter_mut()
+                .filter(|fl| too_inaccurate(fl))
+                .for_each(|fl| fl.low_accuracy = true);
+        } else {
+            file_locations.retain(|fl| !too_inaccurate(fl));
+        }
+    }
+
     pub fn generate_missing_thumbnails(&mut self) {
         // Thumbnailing is CPU-bound (decode + re-encode per image); fan it out.
         self.file_locations
@@ -179,6 +437,10 @@ impl FileSet {
             .for_each(|fl| fl.generate_missing_thumbnail());
     }
 
+    fn sight_line_object<'a>(&self, fl: &'a FileLocation) -> Option<&'a ObjectLocation> {
+        fl.object.as_ref().filter(|_| self.sight_lines)
+    }
+
     pub fn output(&mut self, format: &Option<String>) -> Result<()> {
         // Lock stdout once and wrap it in a BufWriter: a `println!` per feature would
         // otherwise re-acquire the lock and flush on every line. The per-feature
@@ -196,10 +458,19 @@ impl FileSet {
                 let bodies: Vec<String> = self
                     .file_locations
                     .par_iter()
-                    .map(|fl| fl.as_kml())
+                    .flat_map_iter(|fl| {
+                        let track = fl.track.as_ref().map(|t| t.as_kml(&fl.file));
+                        let object = self
+                            .sight_line_object(fl)
+                            .map(|o| o.as_kml(&fl.file, (fl.latitude, fl.longitude)));
+                        std::iter::once(fl.as_kml()).chain(track).chain(object)
+                    })
                     .collect();
                 writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
-                writeln!(out, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+                writeln!(
+                    out,
+                    r#"<kml xmlns="http://www.opengis.net/kml/2.2" xmlns:gx="http://www.google.com/kml/ext/2.2">"#
+                )?;
                 writeln!(out, r#"<Document>"#)?;
                 for body in &bodies {
                     writeln!(out, "{body}")?;
@@ -211,7 +482,13 @@ impl FileSet {
                 let features: Vec<String> = self
                     .file_locations
                     .par_iter()
-                    .map(|fl| fl.as_geojson())
+                    .flat_map_iter(|fl| {
+                        let track = fl.track.as_ref().map(|t| t.as_geojson(&fl.file));
+                        let object = self
+                            .sight_line_object(fl)
+                            .map(|o| o.as_geojson(&fl.file, (fl.latitude, fl.longitude)));
+                        std::iter::once(fl.as_geojson()).chain(track).chain(object)
+                    })
                     .collect();
                 writeln!(out, r#"{{"type": "FeatureCollection","features": ["#)?;
                 let mut comma = "";
@@ -231,7 +508,11 @@ impl FileSet {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::file_location::FileLocation;
+    use crate::description::Description;
+    use crate::file_location::{FileLocation, LocationSource};
+    use crate::gps_accuracy::GpsAccuracy;
+    use crate::track::TrackPoint;
+    use crate::validation::Validity;
     use chrono::NaiveDate;
 
     fn at_midnight(y: i32, m: u32, d: u32) -> NaiveDateTime {
@@ -250,6 +531,7 @@ mod tests {
             direction: None,
             thumbnail: None,
             timestamp: None,
+            ..Default::default()
         }
     }
 
@@ -257,8 +539,35 @@ mod tests {
     fn test_scan_tree_finds_images() {
         let mut fs = FileSet::default();
         fs.scan_tree("test_files").unwrap();
-        assert_eq!(fs.file_locations.len(), 1);
-        assert!(fs.file_locations[0].file.ends_with("sunrise.jpg"));
+        let mut files: Vec<&str> = fs
+            .file_locations
+            .iter()
+            .map(|fl| fl.file.rsplit('/').next().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                "sunrise.avif",
+                "sunrise.cr3",
+                "sunrise.dng",
+                "sunrise.heic",
+                "sunrise.jpg",
+                "sunrise.mov",
+                "sunrise.mp4",
+                "sunrise.orf",
+                "sunrise.png",
+                "sunrise.raf",
+                "sunrise.rw2",
+                "sunrise.srt",
+                "sunrise.webp",
+                "sunrise_dji.jpg",
+                "sunrise_gopro.mp4",
+                "sunrise_iptc.jpg",
+                "sunrise_takeout.jpg",
+                "sunrise_xmp.png"
+            ]
+        );
     }
 
     #[test]
@@ -286,6 +595,46 @@ mod tests {
         assert_eq!(fs.file_locations[0].file, "test_files/sunrise.jpg");
     }
 
+    /// The extension decides which files are opened, the content how they are read: a
+    /// JPEG without an extension is read, one named `.gif` only once `gif` is added to
+    /// the extensions, and a GIF named `.jpg` never.
+    #[test]
+    fn test_add_files_by_content_and_extension() {
+        let dir = std::env::temp_dir().join("img_coords_test_file_types");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+        for name in ["no_extension", "photo.JPE", "misnamed.gif", "other.xyz"] {
+            fs::copy("test_files/sunrise.jpg", dir.join(name)).unwrap();
+        }
+        fs::write(dir.join("real_gif.jpg"), b"GIF89a\x01\0\x01\0\0\0\0;").unwrap();
+        let scan = |extra: &[&str], excluded: &[&str]| {
+            let mut file_set = FileSet::default();
+            let strings = |e: &[&str]| e.iter().map(|e| e.to_string()).collect();
+            file_set.set_extensions(strings(extra), strings(excluded));
+            file_set.scan_tree(dir.to_str().unwrap()).unwrap();
+            let mut names: Vec<String> = file_set
+                .file_locations
+                .iter()
+                .map(|fl| {
+                    assert_eq!(fl.mime_type.as_deref(), Some("image/jpeg"));
+                    Path::new(&fl.file)
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(scan(&[], &[]), ["no_extension", "photo.JPE"]);
+        assert_eq!(
+            scan(&[".GIF", "xyz"], &["jpe"]),
+            ["misnamed.gif", "no_extension", "other.xyz"]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
     #[test]
     fn test_add_files_dedups_against_existing() {
         let mut fs = FileSet::default();
@@ -331,6 +680,136 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_description_filters() {
+        let venice = |file: &str| FileLocation {
+            latitude: 45.4343,
+            longitude: 12.3388,
+            ..location(file)
+        };
+        let described =
+            |file: &str, rating: Option<i32>, label: &str, keywords: &[&str]| FileLocation {
+                description: Some(Description {
+                    rating,
+                    label: Some(label.to_string()).filter(|l| !l.is_empty()),
+                    keywords: keywords.iter().map(|k| k.to_string()).collect(),
+                    ..Default::default()
+                }),
+                ..venice(file)
+            };
+        let locations = vec![
+            described("pick.jpg", Some(5), "Red", &["sunrise", "Lagoon"]),
+            described("good.jpg", Some(4), "green", &["sunrise", "private"]),
+            described("meh.jpg", Some(2), "Red", &["lagoon"]),
+            described("unrated.jpg", None, "", &[]),
+            venice("plain.jpg"),
+        ];
+        let filter = |fs: &FileSet| -> Vec<String> {
+            let mut fs = fs.clone();
+            fs.add_locations(locations.clone());
+            fs.file_locations.into_iter().map(|fl| fl.file).collect()
+        };
+
+        let mut fs = FileSet::default();
+        assert_eq!(filter(&fs).len(), 5, "no filters keep all");
+        fs.set_min_rating(4);
+        assert_eq!(filter(&fs), ["pick.jpg", "good.jpg"]);
+        fs.set_keywords(vec![], vec!["PRIVATE".to_string()]);
+        assert_eq!(filter(&fs), ["pick.jpg"]);
+
+        let mut fs = FileSet::default();
+        fs.set_labels(vec!["red".to_string()]);
+        assert_eq!(filter(&fs), ["pick.jpg", "meh.jpg"]);
+        fs.set_keywords(vec!["lagoon".to_string()], vec![]);
+        assert_eq!(filter(&fs), ["pick.jpg", "meh.jpg"]);
+
+        let mut fs = FileSet::default();
+        fs.set_keywords(vec!["sunrise".to_string()], vec![]);
+        assert_eq!(filter(&fs), ["pick.jpg", "good.jpg"]);
+    }
+
+    #[test]
+    fn test_max_error() {
+        let with_error = |file: &str, metres: f64| FileLocation {
+            accuracy: Some(GpsAccuracy {
+                h_positioning_error: Some(metres),
+                ..Default::default()
+            }),
+            ..location(file)
+        };
+        let locations = vec![
+            with_error("good.jpg", 5.0),
+            with_error("bad.jpg", 2000.0),
+            location("unknown.jpg"), // No accuracy information: always kept
+        ];
+
+        let mut fs = FileSet::default();
+        fs.set_max_error(50.0, false);
+        let mut dropped = locations.clone();
+        fs.apply_max_error(&mut dropped);
+        let files: Vec<&str> = dropped.iter().map(|fl| fl.file.as_str()).collect();
+        assert_eq!(files, vec!["good.jpg", "unknown.jpg"]);
+
+        fs.set_max_error(50.0, true);
+        let mut flagged = locations;
+        fs.apply_max_error(&mut flagged);
+        let flags: Vec<bool> = flagged.iter().map(|fl| fl.low_accuracy).collect();
+        assert_eq!(flags, vec![false, true, false]);
+    }
+
+    #[test]
+    fn test_validation_quarantines_invalid() {
+        let nan = FileLocation {
+            latitude: f64::NAN,
+            ..location("nan.jpg")
+        };
+        let good = FileLocation {
+            latitude: 45.5,
+            longitude: 12.3,
+            ..location("good.jpg")
+        };
+        let null_island = location("null.jpg");
+
+        let mut fs = FileSet::default();
+        let mut locations = vec![nan.clone(), good.clone(), null_island.clone()];
+        fs.apply_validation(&mut locations);
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].file, "good.jpg");
+        assert_eq!(locations[0].validity, Validity::Ok);
+        assert_eq!(fs.quarantine.len(), 2);
+
+        let mut fs = FileSet::default();
+        fs.set_keep_invalid(true);
+        let mut locations = vec![nan, good, null_island];
+        fs.apply_validation(&mut locations);
+        let files: Vec<&str> = locations.iter().map(|fl| fl.file.as_str()).collect();
+        assert_eq!(files, vec!["good.jpg", "null.jpg"]);
+        assert!(locations[1].validity.is_invalid());
+        assert_eq!(fs.quarantine.len(), 2);
+
+        let path = std::env::temp_dir().join("img_coords_test_quarantine.json");
+        fs.write_quarantine_report(path.to_str().unwrap()).unwrap();
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(report[0]["name"], "nan.jpg");
+        assert!(report[0]["latitude"].is_null());
+        assert_eq!(report[1]["reason"], "coordinates are exactly (0, 0)");
+    }
+
+    #[test]
+    fn test_exif_fields_opt_in() {
+        let mut fs = FileSet::default();
+        fs.add_files(vec!["test_files/sunrise.jpg".to_string()]);
+        assert!(fs.file_locations[0].camera.is_none());
+
+        let mut fs = FileSet::default();
+        fs.set_exif_fields(true);
+        fs.add_files(vec!["test_files/sunrise.jpg".to_string()]);
+        let camera = fs.file_locations[0].camera.as_ref().unwrap();
+        assert_eq!(camera.model, Some("Pixel 8 Pro".to_string()));
+    }
+
     #[test]
     fn test_load_from_geojson_parses_features() {
         let data = r#"{"type":"FeatureCollection","features":[
@@ -374,6 +853,123 @@ mod tests {
         assert_eq!(fs.file_locations[0].altitude, Some(46.79));
     }
 
+    /// The sight line feature is skipped on load; the object position comes back from
+    /// the image's own properties.
+    #[test]
+    fn test_sight_lines_roundtrip() {
+        let mut fl = location("a.jpg");
+        fl.latitude = 45.4336;
+        fl.longitude = 12.3388;
+        fl.object = Some(ObjectLocation {
+            latitude: 45.4341,
+            longitude: 12.3397,
+            ..Default::default()
+        });
+        let object = fl.object.as_ref().unwrap();
+        let camera = (fl.latitude, fl.longitude);
+        let geojson = format!(
+            r#"{{"type":"FeatureCollection","features":[{},{}]}}"#,
+            fl.as_geojson(),
+            object.as_geojson(&fl.file, camera)
+        );
+        let kml = format!(
+            r#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document>{}{}</Document></kml>"#,
+            fl.as_kml(),
+            object.as_kml(&fl.file, camera)
+        );
+        for data in [geojson, kml] {
+            let mut fs = FileSet::default();
+            fs.load_from_geojson(&data)
+                .or_else(|_| fs.load_from_kml(&data))
+                .unwrap();
+            assert_eq!(fs.file_locations.len(), 1);
+            assert_eq!(fs.file_locations[0].object, fl.object);
+            assert_eq!(fs.file_locations[0].track, None);
+        }
+        let fs = FileSet::default();
+        assert!(fs.sight_line_object(&fl).is_none());
+        let mut fs = FileSet::default();
+        fs.set_sight_lines(true);
+        assert_eq!(fs.sight_line_object(&fl), fl.object.as_ref());
+    }
+
+    /// A video's track is written after its point and attached to it again on load.
+    #[test]
+    fn test_tracks_roundtrip() {
+        let mut fl = location("a.mp4");
+        fl.track = Some(Track {
+            points: vec![TrackPoint::default(), TrackPoint::default()],
+            start: Some("2025-03-06T05:41:36Z".to_string()),
+            end: None,
+        });
+        let track = fl.track.as_ref().unwrap();
+        let geojson = format!(
+            r#"{{"type":"FeatureCollection","features":[{},{}]}}"#,
+            fl.as_geojson(),
+            track.as_geojson(&fl.file)
+        );
+        let kml = format!(
+            r#"<kml xmlns="http://www.opengis.net/kml/2.2"><Document>{}{}</Document></kml>"#,
+            fl.as_kml(),
+            track.as_kml(&fl.file)
+        );
+        for data in [geojson, kml] {
+            let mut fs = FileSet::default();
+            fs.load_from_geojson(&data)
+                .or_else(|_| fs.load_from_kml(&data))
+                .unwrap();
+            assert_eq!(fs.file_locations.len(), 1);
+            assert_eq!(fs.file_locations[0].track, fl.track);
+        }
+    }
+
+    #[test]
+    fn test_import_exiftool_json() {
+        let mut fs = FileSet::default();
+        fs.add_files(vec!["test_files/sunrise.jpg".to_string()]);
+        fs.set_after(at_midnight(2025, 1, 1));
+        let json = r#"[
+            {"SourceFile": "test_files/sunrise.jpg", "GPSLatitude": 1.0, "GPSLongitude": 2.0},
+            {"SourceFile": "a/b.x3f", "GPSLatitude": 45.5, "GPSLatitudeRef": "N",
+             "GPSLongitude": 12.3, "GPSLongitudeRef": "E", "DateTimeOriginal": "2025:03:06 05:41:42"},
+            {"SourceFile": "a/old.x3f", "GPSLatitude": 45.5, "GPSLongitude": 12.3,
+             "DateTimeOriginal": "2019:03:06 05:41:42"},
+            {"SourceFile": "a/none.x3f", "DateTimeOriginal": "2025:03:06 05:41:42"}
+        ]"#;
+        fs.import_exiftool_json(json).unwrap();
+        let files: Vec<&str> = fs
+            .file_locations
+            .iter()
+            .map(|fl| fl.file.as_str())
+            .collect();
+        assert_eq!(files, ["test_files/sunrise.jpg", "a/b.x3f"]);
+        assert_eq!(fs.file_locations[0].latitude, 45.50566666666667); // Kept as it was
+        assert_eq!(
+            fs.file_locations[1].location_source,
+            Some(LocationSource::Exiftool)
+        );
+        assert!(fs.import_exiftool_json(r#"{"SourceFile": "a"}"#).is_err());
+    }
+
+    #[test]
+    fn test_load_lightroom_catalog() {
+        let path = crate::lightroom::tests::make_catalog("img_coords_test_file_set.lrcat");
+        let mut fs = FileSet::default();
+        fs.file_locations.push(location("/photos/2025/03/noon.jpg"));
+        fs.load_lightroom_catalog(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(fs.file_locations.len(), 2);
+        assert_eq!(fs.file_locations[0].location_source, None); // Kept as it was
+        let fl = &fs.file_locations[1];
+        assert_eq!(fl.file, "/photos/2025/03/sunrise.CR2");
+        assert_eq!(fl.timestamp, Some("2025:03:06 05:41:42".to_string()));
+        assert_eq!(
+            fl.timestamp_utc,
+            Some("2025-03-06T04:41:42.125Z".to_string())
+        );
+        assert_eq!(fl.location_source, Some(LocationSource::Lightroom));
+    }
+
     #[test]
     fn test_load_from_file_missing_path_errors() {
         let mut fs = FileSet::default();
@@ -0,0 +1,160 @@
+//! Detects the container format of a file from its first bytes, so files are read
+//! whatever their extension, and formats that never carry a position are skipped.
+use crate::heif::HEIF_BRANDS;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Top-level atoms that old QuickTime files without `ftyp` start with
+const QUICKTIME_ATOMS: &[&[u8; 4]] = &[b"moov", b"mdat", b"wide", b"free", b"skip", b"pnot"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FileType {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+    /// TIFF and the raw formats built on it (DNG, CR2, NEF, ARW, ORF, RW2)
+    Tiff,
+    Heif,
+    Avif,
+    Cr3,
+    Raf,
+    QuickTime,
+    Mp4,
+}
+
+impl FileType {
+    pub(crate) fn from_bytes(head: &[u8]) -> Option<Self> {
+        let at =
+            |offset: usize, magic: &[u8]| head.get(offset..offset + magic.len()) == Some(magic);
+        if at(0, &[0xff, 0xd8, 0xff]) {
+            return Some(Self::Jpeg);
+        }
+        if at(0, b"\x89PNG\r\n\x1a\n") {
+            return Some(Self::Png);
+        }
+        if at(0, b"GIF87a") || at(0, b"GIF89a") {
+            return Some(Self::Gif);
+        }
+        if at(0, b"RIFF") && at(8, b"WEBP") {
+            return Some(Self::Webp);
+        }
+        // ORF and RW2 use their own magic numbers in place of 42
+        if [b"II*\0", b"MM\0*", b"IIRO", b"IIRS", b"IIU\0"]
+            .iter()
+            .any(|magic| at(0, *magic))
+        {
+            return Some(Self::Tiff);
+        }
+        if at(0, b"FUJIFILMCCD-RAW") {
+            return Some(Self::Raf);
+        }
+        let brand: &[u8; 4] = head.get(8..12)?.try_into().ok()?;
+        if at(4, b"ftyp") {
+            return Some(match brand {
+                b"avif" | b"avis" => Self::Avif,
+                b"crx " => Self::Cr3,
+                b"qt  " => Self::QuickTime,
+                brand if HEIF_BRANDS.contains(brand) => Self::Heif,
+                _ => Self::Mp4,
+            });
+        }
+        let atom: &[u8; 4] = head.get(4..8)?.try_into().ok()?;
+        QUICKTIME_ATOMS.contains(&atom).then_some(Self::QuickTime)
+    }
+
+    /// Reads the first bytes of a file, then rewinds it. `None` if it cannot be read or
+    /// is of no known type, such as a DJI SRT flight log, which is plain text.
+    pub(crate) fn from_reader<R: Read + Seek>(reader: &mut R) -> Option<Self> {
+        let mut head = Vec::with_capacity(16);
+        reader.seek(SeekFrom::Start(0)).ok()?;
+        let read = reader.by_ref().take(16).read_to_end(&mut head);
+        reader.seek(SeekFrom::Start(0)).ok()?;
+        read.ok()?;
+        Self::from_bytes(&head)
+    }
+
+    pub(crate) fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Gif => "image/gif",
+            Self::Webp => "image/webp",
+            Self::Tiff => "image/tiff",
+            Self::Heif => "image/heif",
+            Self::Avif => "image/avif",
+            Self::Cr3 => "image/x-canon-cr3",
+            Self::Raf => "image/x-fuji-raf",
+            Self::QuickTime => "video/quicktime",
+            Self::Mp4 => "video/mp4",
+        }
+    }
+
+    /// GIF has no place for EXIF or XMP position data.
+    pub(crate) fn may_have_location(&self) -> bool {
+        *self != Self::Gif
+    }
+}
+
+/// A file opened once for all the parsers to read in turn, with its sniffed type.
+pub(crate) struct OpenedFile {
+    pub(crate) reader: BufReader<File>,
+    pub(crate) file_type: Option<FileType>,
+}
+
+impl OpenedFile {
+    pub(crate) fn open(path: impl AsRef<Path>) -> Option<Self> {
+        let mut reader = BufReader::new(File::open(path).ok()?);
+        let file_type = FileType::from_reader(&mut reader);
+        Some(Self { reader, file_type })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_file(path: &str) -> Option<FileType> {
+        OpenedFile::open(path)?.file_type
+    }
+
+    #[test]
+    fn test_from_file() {
+        for (file, expected) in [
+            ("sunrise.jpg", FileType::Jpeg),
+            ("sunrise.png", FileType::Png),
+            ("sunrise.webp", FileType::Webp),
+            ("sunrise.dng", FileType::Tiff),
+            ("sunrise.orf", FileType::Tiff),
+            ("sunrise.rw2", FileType::Tiff),
+            ("sunrise.heic", FileType::Heif),
+            ("sunrise.avif", FileType::Avif),
+            ("sunrise.cr3", FileType::Cr3),
+            ("sunrise.raf", FileType::Raf),
+            ("sunrise.mov", FileType::QuickTime),
+            ("sunrise.mp4", FileType::Mp4),
+        ] {
+            let path = format!("test_files/{file}");
+            assert_eq!(from_file(&path), Some(expected), "{file}");
+        }
+        assert_eq!(from_file("test_files/sunrise.srt"), None);
+        assert_eq!(from_file("test_files/nonexistent.jpg"), None);
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        assert_eq!(FileType::from_bytes(b"GIF89a\x01\0"), Some(FileType::Gif));
+        assert!(!FileType::Gif.may_have_location());
+        assert_eq!(
+            FileType::from_bytes(b"\0\0\0\x08wide\0\0\0\0mdat"),
+            Some(FileType::QuickTime)
+        );
+        assert_eq!(
+            FileType::from_bytes(b"\0\0\0\x18ftypmiaf\0\0\0\0"),
+            Some(FileType::Heif)
+        );
+        assert_eq!(FileType::from_bytes(b"\xff\xd8"), None); // Truncated
+        assert_eq!(FileType::from_bytes(b"1\n00:00:00,000 -->"), None);
+    }
+}
@@ -0,0 +1,373 @@
+//! GoPro GPMF telemetry: the GPS5 samples in the `gpmd` metadata track of a GoPro MP4,
+//! as a track. Each track sample holds about a second of telemetry as nested KLV
+//! (key, type, size, repeat) entries; a `STRM` with `GPS5` data also carries its
+//! scale factors (`SCAL`), the UTC time of its first fix (`GPSU`) and the fix type
+//! (`GPSF`).
+use crate::isobmff::{self, ByteReader};
+use crate::track::{Track, TrackPoint};
+use chrono::{NaiveDateTime, SecondsFormat, TimeDelta};
+use std::io::{Read, Seek};
+
+const MAX_MOOV_SIZE: u64 = 64 * 1024 * 1024;
+const MAX_SAMPLE_SIZE: u64 = 1024 * 1024;
+/// GPSF values below this are no fix or an unreliable one
+const MIN_FIX_TYPE: u32 = 2;
+
+/// One KLV entry of a GPMF payload.
+struct Klv<'a> {
+    key: [u8; 4],
+    value_type: u8,
+    struct_size: usize,
+    repeat: usize,
+    data: &'a [u8],
+}
+
+/// Where a sample of the telemetry track is, and how long it lasts.
+struct Sample {
+    offset: u64,
+    size: u64,
+    duration: f64, // Seconds
+}
+
+/// Reads the GPS track of a GoPro video. Returns `None` if it has no GPS fixes. Samples
+/// that cannot be read, as at the end of a file cut short, are skipped.
+pub(crate) fn track_from_video<R: Read + Seek>(reader: &mut R) -> Option<Track> {
+    let boxes = isobmff::top_level_boxes(reader).ok()?;
+    let moov = boxes.iter().find(|b| &b.box_type == b"moov")?;
+    let moov = isobmff::read_box_body(reader, moov, MAX_MOOV_SIZE).ok()?;
+    let samples = isobmff::child_boxes(&moov)
+        .into_iter()
+        .filter(|(t, _)| t == b"trak")
+        .find_map(|(_, trak)| gpmd_samples(trak))?;
+
+    let mut track = Track::default();
+    let mut last_fix = None;
+    for sample in samples {
+        let Ok(data) = isobmff::read_at(reader, sample.offset, sample.size, MAX_SAMPLE_SIZE) else {
+            continue;
+        };
+        for (time, mut points) in gps5_streams(&data) {
+            let count = points.len();
+            if count == 0 {
+                continue;
+            }
+            if let Some(time) = time {
+                track.start.get_or_insert_with(|| rfc3339(time));
+                // Fixes are evenly spread over the sample
+                let step = sample.duration / count as f64;
+                last_fix = Some(
+                    time + TimeDelta::milliseconds((step * (count - 1) as f64 * 1000.0) as i64),
+                );
+            }
+            track.points.append(&mut points);
+        }
+    }
+    track.end = last_fix.map(rfc3339);
+    (!track.points.is_empty()).then_some(track)
+}
+
+fn rfc3339(time: NaiveDateTime) -> String {
+    time.and_utc().to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// The samples of a `trak`, if it is the GPMF (`gpmd`) metadata track. The list stops
+/// where `stsz` runs out of sizes.
+fn gpmd_samples(trak: &[u8]) -> Option<Vec<Sample>> {
+    let mdia = isobmff::find_child(trak, b"mdia")?;
+    let stbl = isobmff::find_child(isobmff::find_child(mdia, b"minf")?, b"stbl")?;
+    let mut r = ByteReader::new(isobmff::find_child(stbl, b"stsd")?);
+    r.full_box_header()?;
+    r.u32()?; // Entry count
+    let (format, _) = isobmff::child_boxes(r.rest()).into_iter().next()?;
+    if &format != b"gpmd" {
+        return None;
+    }
+
+    let timescale = media_timescale(isobmff::find_child(mdia, b"mdhd")?)?;
+    let sizes = sample_sizes(isobmff::find_child(stbl, b"stsz")?)?;
+    let chunk_offsets = match isobmff::find_child(stbl, b"co64") {
+        Some(co64) => chunk_offsets(co64, 8)?,
+        None => chunk_offsets(isobmff::find_child(stbl, b"stco")?, 4)?,
+    };
+    let samples_per_chunk = samples_per_chunk(isobmff::find_child(stbl, b"stsc")?)?;
+    let durations = isobmff::find_child(stbl, b"stts")
+        .and_then(sample_durations)
+        .unwrap_or_default();
+
+    let mut ret = Vec::with_capacity(sizes.len());
+    let mut sizes = sizes.into_iter();
+    for (chunk, chunk_offset) in chunk_offsets.into_iter().enumerate() {
+        // stsc lists the chunk (numbered from 1) where each run of chunks starts
+        let per_chunk = samples_per_chunk
+            .iter()
+            .rev()
+            .find(|(first, _)| *first as usize <= chunk + 1)
+            .map_or(1, |(_, n)| *n);
+        let mut offset = chunk_offset;
+        for _ in 0..per_chunk {
+            let Some(size) = sizes.next() else {
+                return Some(ret);
+            };
+            let duration = durations.get(ret.len()).copied().unwrap_or(0);
+            ret.push(Sample {
+                offset,
+                size,
+                duration: duration as f64 / timescale as f64,
+            });
+            let Some(next) = offset.checked_add(size) else {
+                return Some(ret);
+            };
+            offset = next;
+        }
+    }
+    Some(ret)
+}
+
+fn media_timescale(mdhd: &[u8]) -> Option<u32> {
+    let mut r = ByteReader::new(mdhd);
+    let (version, _) = r.full_box_header()?;
+    r.skip(if version == 1 { 16 } else { 8 })?; // Creation and modification time
+    r.u32().filter(|t| *t > 0)
+}
+
+fn sample_sizes(stsz: &[u8]) -> Option<Vec<u64>> {
+    let mut r = ByteReader::new(stsz);
+    r.full_box_header()?;
+    let (size, count) = (r.u32()?, r.u32()?);
+    (0..count)
+        .map(|_| match size {
+            0 => r.u32().map(u64::from),
+            size => Some(size as u64),
+        })
+        .collect()
+}
+
+fn chunk_offsets(stco: &[u8], offset_size: usize) -> Option<Vec<u64>> {
+    let mut r = ByteReader::new(stco);
+    r.full_box_header()?;
+    let count = r.u32()?;
+    (0..count).map(|_| r.sized(offset_size)).collect()
+}
+
+/// (first chunk, samples per chunk) runs from `stsc`
+fn samples_per_chunk(stsc: &[u8]) -> Option<Vec<(u32, u32)>> {
+    let mut r = ByteReader::new(stsc);
+    r.full_box_header()?;
+    let count = r.u32()?;
+    (0..count)
+        .map(|_| {
+            let (first, per_chunk, _description) = (r.u32()?, r.u32()?, r.u32()?);
+            Some((first, per_chunk))
+        })
+        .collect()
+}
+
+/// Per-sample durations in media timescale units, expanded from the `stts` runs.
+fn sample_durations(stts: &[u8]) -> Option<Vec<u32>> {
+    let mut r = ByteReader::new(stts);
+    r.full_box_header()?;
+    let count = r.u32()?;
+    let mut ret = vec![];
+    for _ in 0..count {
+        let (samples, delta) = (r.u32()?, r.u32()?);
+        ret.extend(std::iter::repeat_n(delta, samples as usize));
+    }
+    Some(ret)
+}
+
+fn parse_klv(data: &[u8]) -> Vec<Klv<'_>> {
+    let mut ret = vec![];
+    let mut r = ByteReader::new(data);
+    while r.remaining() >= 8 {
+        let (Some(key), Some(header)) = (r.array4(), r.array4()) else {
+            break;
+        };
+        let [value_type, struct_size, repeat_hi, repeat_lo] = header;
+        let struct_size = struct_size as usize;
+        let repeat = u16::from_be_bytes([repeat_hi, repeat_lo]) as usize;
+        let len = struct_size * repeat;
+        let Some(data) = r.take(len) else {
+            break;
+        };
+        r.skip((4 - len % 4) % 4); // Padded to 32 bits; the last entry may not be
+        ret.push(Klv {
+            key,
+            value_type,
+            struct_size,
+            repeat,
+            data,
+        });
+    }
+    ret
+}
+
+/// The GPS5 streams in a GPMF payload, with the time of their first fix. Streams
+/// without a usable fix are left out.
+fn gps5_streams(payload: &[u8]) -> Vec<(Option<NaiveDateTime>, Vec<TrackPoint>)> {
+    let mut ret = vec![];
+    for devc in parse_klv(payload).iter().filter(|k| &k.key == b"DEVC") {
+        for strm in parse_klv(devc.data).iter().filter(|k| &k.key == b"STRM") {
+            let entries = parse_klv(strm.data);
+            let find = |key: &[u8; 4]| entries.iter().find(|k| &k.key == key);
+            let Some(gps5) = find(b"GPS5") else {
+                continue;
+            };
+            let fix = find(b"GPSF").and_then(|k| ByteReader::new(k.data).u32());
+            if fix.is_some_and(|fix| fix < MIN_FIX_TYPE) {
+                continue;
+            }
+            let scales = find(b"SCAL").map(scale_values).unwrap_or_default();
+            let points = gps5_points(gps5, &scales);
+            if points.is_empty() {
+                continue;
+            }
+            let time = find(b"GPSU").and_then(|k| parse_gpsu(k.data));
+            ret.push((time, points));
+        }
+    }
+    ret
+}
+
+/// SCAL holds one divisor per GPS5 value, or a single one for all of them.
+fn scale_values(scal: &Klv) -> Vec<f64> {
+    let mut r = ByteReader::new(scal.data);
+    (0..scal.repeat)
+        .filter_map(|_| match (scal.value_type, scal.struct_size) {
+            (b'l' | b'L', 4) => r.u32().map(|v| v as i32 as f64),
+            (b's' | b'S', 2) => r.u16().map(|v| v as i16 as f64),
+            _ => None,
+        })
+        .collect()
+}
+
+/// GPS5: latitude, longitude, altitude, 2D and 3D speed, as scaled signed 32-bit integers.
+fn gps5_points(gps5: &Klv, scales: &[f64]) -> Vec<TrackPoint> {
+    let scale = |i: usize| match scales.get(i).or(scales.last()) {
+        Some(s) if *s != 0.0 => *s,
+        _ => 1.0,
+    };
+    if gps5.value_type != b'l' || gps5.struct_size != 20 {
+        return vec![];
+    }
+    gps5.data
+        .chunks_exact(20)
+        .map(|fix| {
+            let value = |i: usize| {
+                i32::from_be_bytes(fix[i * 4..i * 4 + 4].try_into().unwrap()) as f64 / scale(i)
+            };
+            TrackPoint {
+                latitude: value(0),
+                longitude: value(1),
+                altitude: Some(value(2)),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// GPSU is the UTC time as "yymmddhhmmss.sss".
+fn parse_gpsu(data: &[u8]) -> Option<NaiveDateTime> {
+    let s = std::str::from_utf8(data).ok()?;
+    NaiveDateTime::parse_from_str(s.trim_end_matches('\0'), "%y%m%d%H%M%S%.f").ok()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Builds a KLV entry, padded to 32 bits.
+    pub(crate) fn klv(key: &[u8; 4], value_type: u8, struct_size: u8, data: &[u8]) -> Vec<u8> {
+        let repeat = match struct_size {
+            0 => 0,
+            size => data.len() / size as usize,
+        } as u16;
+        let mut ret = key.to_vec();
+        ret.extend([value_type, struct_size]);
+        ret.extend(repeat.to_be_bytes());
+        ret.extend(data);
+        ret.resize(ret.len().div_ceil(4) * 4, 0);
+        ret
+    }
+
+    /// A DEVC/STRM payload with the given fixes (degrees and metres) and fix type.
+    pub(crate) fn gps5_payload(gpsu: &str, fix: u32, fixes: &[(f64, f64, f64)]) -> Vec<u8> {
+        let scales: Vec<u8> = [10_000_000i32, 10_000_000, 1000, 1000, 100]
+            .iter()
+            .flat_map(|s| s.to_be_bytes())
+            .collect();
+        let gps5: Vec<u8> = fixes
+            .iter()
+            .flat_map(|(lat, lon, alt)| {
+                let scaled = |v: f64, scale: f64| (v * scale).round() as i32;
+                let values = [
+                    scaled(*lat, 1e7),
+                    scaled(*lon, 1e7),
+                    scaled(*alt, 1e3),
+                    0,
+                    0,
+                ];
+                values.into_iter().flat_map(|v| v.to_be_bytes())
+            })
+            .collect();
+        let mut strm = klv(
+            b"STNM",
+            b'c',
+            1,
+            b"GPS (Lat., Long., Alt., 2D speed, 3D speed)",
+        );
+        strm.extend(klv(b"GPSF", b'L', 4, &fix.to_be_bytes()));
+        strm.extend(klv(b"GPSU", b'U', 16, gpsu.as_bytes()));
+        strm.extend(klv(b"SCAL", b'l', 4, &scales));
+        strm.extend(klv(b"GPS5", b'l', 20, &gps5));
+        let mut devc = klv(b"DVNM", b'c', 1, b"Camera");
+        devc.extend(klv(b"STRM", 0, 4, &strm));
+        klv(b"DEVC", 0, 4, &devc)
+    }
+
+    #[test]
+    fn test_gps5_streams() {
+        let payload = gps5_payload(
+            "250306054136.000",
+            3,
+            &[(45.5056667, 12.3411611, 46.79), (45.5057, 12.3412, 47.0)],
+        );
+        let streams = gps5_streams(&payload);
+        assert_eq!(streams.len(), 1);
+        let (time, points) = &streams[0];
+        assert_eq!(rfc3339(time.unwrap()), "2025-03-06T05:41:36Z");
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].latitude, 45.5056667);
+        assert_eq!(points[0].longitude, 12.3411611);
+        assert_eq!(points[0].altitude, Some(46.79));
+    }
+
+    #[test]
+    fn test_no_fix_is_skipped() {
+        let payload = gps5_payload("250306054136.000", 0, &[(45.5, 12.3, 0.0)]);
+        assert!(gps5_streams(&payload).is_empty());
+        let payload = gps5_payload("250306054136.000", 3, &[]);
+        assert!(gps5_streams(&payload).is_empty());
+    }
+
+    /// The first of the two samples of sunrise_gopro.mp4 with its GPS5 repeat zeroed
+    #[test]
+    fn test_track_from_video_empty_gps5() {
+        let mut data = std::fs::read("test_files/sunrise_gopro.mp4").unwrap();
+        let gps5 = data.windows(4).position(|w| w == b"GPS5").unwrap();
+        data[gps5 + 6..gps5 + 8].copy_from_slice(&[0, 0]);
+        let track = track_from_video(&mut std::io::Cursor::new(data)).unwrap();
+        assert_eq!(track.points.len(), 2);
+        assert_eq!(track.points[1].latitude, 45.50578);
+    }
+
+    /// A file cut short in its second sample keeps the fixes of the first one
+    #[test]
+    fn test_track_from_video_truncated() {
+        let mut data = std::fs::read("test_files/sunrise_gopro.mp4").unwrap();
+        data.truncate(700);
+        let track = track_from_video(&mut std::io::Cursor::new(data)).unwrap();
+        assert_eq!(track.points.len(), 2);
+        assert_eq!(track.points[0].latitude, 45.5056667);
+        assert_eq!(track.start, Some("2025-03-06T05:41:36Z".to_string()));
+    }
+}
@@ -0,0 +1,104 @@
+use crate::file_location::{
+    exif_rational, exif_string, property_f64, property_string, property_u32, push_property,
+};
+use exif::{Exif, Tag};
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+
+/// Typical user equivalent range error of a consumer GPS receiver, used to turn a DOP
+/// value into metres when no `GPSHPositioningError` is given.
+const DOP_UERE_METRES: f64 = 5.0;
+
+/// GPS fix quality, as far as the image records it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GpsAccuracy {
+    pub h_positioning_error: Option<f64>, // metres
+    pub dop: Option<f64>,
+    pub measure_mode: Option<u32>,  // 2 or 3 (dimensional)
+    pub satellites: Option<String>, // free text, often a count
+    pub status: Option<String>,     // "A" (active) or "V" (void)
+}
+
+impl GpsAccuracy {
+    /// Returns `None` if the image carries none of the fields.
+    pub(crate) fn from_exif(exif: &Exif) -> Option<Self> {
+        let string = |tag: Tag| exif_string(exif, tag);
+        let rational = |tag: Tag| exif_rational(exif, tag);
+        let ret = Self {
+            h_positioning_error: rational(Tag::GPSHPositioningError),
+            dop: rational(Tag::GPSDOP),
+            measure_mode: string(Tag::GPSMeasureMode).and_then(|s| s.parse().ok()),
+            satellites: string(Tag::GPSSatellites),
+            status: string(Tag::GPSStatus),
+        };
+        (ret != Self::default()).then_some(ret)
+    }
+
+    /// Best guess of the horizontal error in metres, if the image says anything about it.
+    pub fn estimated_error(&self) -> Option<f64> {
+        self.h_positioning_error
+            .or_else(|| self.dop.map(|dop| dop * DOP_UERE_METRES))
+    }
+
+    /// The fields with a `gps_` prefix, as the EXIF tags they come from are named. The
+    /// estimated error is not written; it is derived again when needed.
+    pub(crate) fn properties(&self) -> Vec<(&'static str, serde_json::Value)> {
+        let mut ret = Vec::new();
+        push_property(
+            &mut ret,
+            "gps_h_positioning_error",
+            self.h_positioning_error,
+        );
+        push_property(&mut ret, "gps_dop", self.dop);
+        push_property(&mut ret, "gps_measure_mode", self.measure_mode);
+        push_property(&mut ret, "gps_satellites", self.satellites.as_ref());
+        push_property(&mut ret, "gps_status", self.status.as_ref());
+        ret
+    }
+
+    pub(crate) fn from_properties(properties: &Map<String, serde_json::Value>) -> Option<Self> {
+        let ret = Self {
+            h_positioning_error: property_f64(properties, "gps_h_positioning_error"),
+            dop: property_f64(properties, "gps_dop"),
+            measure_mode: property_u32(properties, "gps_measure_mode"),
+            satellites: property_string(properties, "gps_satellites"),
+            status: property_string(properties, "gps_status"),
+        };
+        (ret != Self::default()).then_some(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimated_error() {
+        let mut acc = GpsAccuracy {
+            dop: Some(2.0),
+            ..Default::default()
+        };
+        assert_eq!(acc.estimated_error(), Some(10.0));
+        acc.h_positioning_error = Some(3.5);
+        assert_eq!(acc.estimated_error(), Some(3.5));
+        assert_eq!(GpsAccuracy::default().estimated_error(), None);
+    }
+
+    /// A satellite count of "07" must stay text, and a void fix on its own still counts
+    #[test]
+    fn test_from_properties() {
+        let properties = serde_json::json!({"gps_satellites": "07", "gps_dop": "1.5"});
+        let acc = GpsAccuracy::from_properties(properties.as_object().unwrap()).unwrap();
+        assert_eq!(acc.satellites.as_deref(), Some("07"));
+        assert_eq!(acc.estimated_error(), Some(7.5));
+        let void = serde_json::json!({"gps_status": "V", "gps_measure_mode": "x"});
+        assert_eq!(
+            GpsAccuracy::from_properties(void.as_object().unwrap()),
+            Some(GpsAccuracy {
+                status: Some("V".to_string()),
+                ..Default::default()
+            })
+        );
+        assert_eq!(GpsAccuracy::from_properties(&Map::new()), None);
+    }
+}
@@ -0,0 +1,240 @@
+//! Locates the Exif and XMP items in HEIF-family images (HEIC, HEIF, AVIF) via the
+//! `meta` box. The EXIF crate handles the common case of Exif itself; this covers files
+//! it rejects, such as those only declaring `heic`/`avif` brands or carrying more than
+//! 64 KiB of Exif data.
+use crate::isobmff::{self, ByteReader};
+use std::io::{Read, Seek};
+
+/// `ftyp` brands of the HEIF family; also what `FileType` sniffs as HEIF and AVIF
+pub(crate) const HEIF_BRANDS: &[[u8; 4]] = &[
+    *b"mif1", *b"msf1", *b"miaf", *b"heic", *b"heix", *b"heim", *b"heis", *b"hevc", *b"hevx",
+    *b"avif", *b"avis",
+];
+const MAX_META_SIZE: u64 = 16 * 1024 * 1024;
+const MAX_ITEM_SIZE: u64 = 16 * 1024 * 1024;
+const XMP_CONTENT_TYPE: &[u8] = b"application/rdf+xml";
+
+/// Where an item's data lives, from the `iloc` box.
+struct ItemLocation {
+    construction_method: u8,
+    base_offset: u64,
+    extents: Vec<(u64, u64)>, // offset, length
+}
+
+/// Returns the TIFF-format Exif block of a HEIF-family file, if it has one.
+pub(crate) fn exif_from_heif<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    let data = item_data(reader, |item_type, _| item_type == b"Exif")?;
+    // The item starts with the offset of the TIFF header, usually past an "Exif\0\0".
+    let mut r = ByteReader::new(&data);
+    let tiff_offset = r.u32()? as usize;
+    r.skip(tiff_offset)?;
+    Some(r.rest().to_vec())
+}
+
+/// Returns the XMP packet of a HEIF-family file: a `mime` item of the RDF content type.
+pub(crate) fn xmp_from_heif<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    item_data(reader, |item_type, rest| {
+        // The item name, then the content type, both NUL-terminated
+        let mut strings = rest.split(|b| *b == 0);
+        item_type == b"mime" && strings.nth(1) == Some(XMP_CONTENT_TYPE)
+    })
+}
+
+/// The data of the first item whose `infe` entry matches `wanted`, which is given the
+/// item type and the rest of the entry.
+fn item_data<R: Read + Seek>(
+    reader: &mut R,
+    wanted: impl Fn(&[u8; 4], &[u8]) -> bool,
+) -> Option<Vec<u8>> {
+    let boxes = isobmff::top_level_boxes(reader).ok()?;
+    let ftyp = boxes.iter().find(|b| &b.box_type == b"ftyp")?;
+    let ftyp = isobmff::read_box_body(reader, ftyp, 1024).ok()?;
+    if !is_heif(&ftyp) {
+        return None;
+    }
+    let meta = boxes.iter().find(|b| &b.box_type == b"meta")?;
+    let meta = isobmff::read_box_body(reader, meta, MAX_META_SIZE).ok()?;
+    let mut r = ByteReader::new(&meta);
+    r.full_box_header()?;
+    let meta = r.rest();
+
+    let item_id = item_id(isobmff::find_child(meta, b"iinf")?, wanted)?;
+    let location = item_location(isobmff::find_child(meta, b"iloc")?, item_id)?;
+    let mut data = Vec::new();
+    for (offset, length) in &location.extents {
+        let offset = location.base_offset.checked_add(*offset)?;
+        match location.construction_method {
+            0 => {
+                let extent = isobmff::read_at(reader, offset, *length, MAX_ITEM_SIZE).ok()?;
+                data.extend(extent);
+            }
+            1 => {
+                let idat = isobmff::find_child(meta, b"idat")?;
+                let start = usize::try_from(offset).ok()?;
+                let end = start.checked_add(usize::try_from(*length).ok()?)?;
+                data.extend_from_slice(idat.get(start..end)?);
+            }
+            _ => return None,
+        }
+        if data.len() as u64 > MAX_ITEM_SIZE {
+            return None;
+        }
+    }
+    Some(data)
+}
+
+fn is_heif(ftyp: &[u8]) -> bool {
+    let mut r = ByteReader::new(ftyp);
+    let Some(major) = r.array4() else {
+        return false;
+    };
+    let _minor_version = r.u32();
+    let mut brands = vec![major];
+    while let Some(brand) = r.array4() {
+        brands.push(brand);
+    }
+    brands.iter().any(|b| HEIF_BRANDS.contains(b))
+}
+
+fn item_id(iinf: &[u8], wanted: impl Fn(&[u8; 4], &[u8]) -> bool) -> Option<u32> {
+    let mut r = ByteReader::new(iinf);
+    let (version, _) = r.full_box_header()?;
+    match version {
+        0 => r.skip(2)?,
+        _ => r.skip(4)?,
+    }
+    isobmff::child_boxes(r.rest())
+        .into_iter()
+        .filter(|(t, _)| t == b"infe")
+        .find_map(|(_, infe)| {
+            let mut r = ByteReader::new(infe);
+            let (version, _) = r.full_box_header()?;
+            let item_id = match version {
+                2 => r.u16()? as u32,
+                3 => r.u32()?,
+                _ => return None,
+            };
+            r.skip(2)?; // item_protection_index
+            let item_type = r.array4()?;
+            wanted(&item_type, r.rest()).then_some(item_id)
+        })
+}
+
+fn item_location(iloc: &[u8], wanted: u32) -> Option<ItemLocation> {
+    let mut r = ByteReader::new(iloc);
+    let (version, _) = r.full_box_header()?;
+    let sizes = r.u16()? as usize;
+    let (offset_size, length_size, base_offset_size) =
+        (sizes >> 12, (sizes >> 8) & 0xf, (sizes >> 4) & 0xf);
+    let index_size = match version {
+        1 | 2 => sizes & 0xf,
+        _ => 0,
+    };
+    let item_count = match version {
+        0 | 1 => r.u16()? as u32,
+        2 => r.u32()?,
+        _ => return None,
+    };
+    for _ in 0..item_count {
+        let item_id = match version {
+            2 => r.u32()?,
+            _ => r.u16()? as u32,
+        };
+        let construction_method = match version {
+            1 | 2 => (r.u16()? & 0xf) as u8,
+            _ => 0,
+        };
+        let _data_reference_index = r.u16()?;
+        let base_offset = r.sized(base_offset_size)?;
+        let extent_count = r.u16()?;
+        let mut extents = Vec::with_capacity(extent_count as usize);
+        for _ in 0..extent_count {
+            r.sized(index_size)?;
+            let offset = r.sized(offset_size)?;
+            let length = r.sized(length_size)?;
+            extents.push((offset, length));
+        }
+        if item_id == wanted {
+            return Some(ItemLocation {
+                construction_method,
+                base_offset,
+                extents,
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isobmff::tests::make_box;
+    use std::io::Cursor;
+
+    /// A HEIF file holding nothing but one item, stored in `idat` (construction
+    /// method 1) or after the `meta` box (method 0). `item_info` is the item type and
+    /// name, and the content type of `mime` items.
+    fn heif_with_item(brands: &[u8], item_info: &[u8], item: &[u8], in_idat: bool) -> Vec<u8> {
+        let mut ftyp = brands[0..4].to_vec();
+        ftyp.extend_from_slice(&[0, 0, 0, 0]);
+        ftyp.extend_from_slice(brands);
+        let ftyp = make_box(b"ftyp", &ftyp);
+
+        let mut infe = vec![2, 0, 0, 0, 0, 1, 0, 0];
+        infe.extend_from_slice(item_info);
+        let mut iinf = vec![0, 0, 0, 0, 0, 1];
+        iinf.extend(make_box(b"infe", &infe));
+
+        let build = |offset: u32| {
+            // Version 1, 4-byte offsets and lengths, no base offset or index
+            let mut iloc = vec![1, 0, 0, 0, 0x44, 0x00, 0, 1, 0, 1];
+            iloc.extend_from_slice(&[0, if in_idat { 1 } else { 0 }, 0, 0, 0, 1]);
+            iloc.extend_from_slice(&offset.to_be_bytes());
+            iloc.extend_from_slice(&(item.len() as u32).to_be_bytes());
+            let mut meta = vec![0, 0, 0, 0];
+            meta.extend(make_box(b"hdlr", &[0; 24]));
+            meta.extend(make_box(b"iinf", &iinf));
+            meta.extend(make_box(b"iloc", &iloc));
+            if in_idat {
+                meta.extend(make_box(b"idat", item));
+            }
+            make_box(b"meta", &meta)
+        };
+        let meta_len = build(0).len();
+        let mut ret = ftyp.clone();
+        if in_idat {
+            ret.extend(build(0));
+        } else {
+            ret.extend(build((ftyp.len() + meta_len + 8) as u32));
+            ret.extend(make_box(b"mdat", item));
+        }
+        ret
+    }
+
+    #[test]
+    fn test_exif_from_heif() {
+        let mut item = vec![0, 0, 0, 6];
+        item.extend_from_slice(b"Exif\0\0MM\0*TIFF");
+        for in_idat in [false, true] {
+            let data = heif_with_item(b"avif", b"Exif\0", &item, in_idat);
+            let exif = exif_from_heif(&mut Cursor::new(data)).unwrap();
+            assert_eq!(exif, b"MM\0*TIFF");
+        }
+    }
+
+    #[test]
+    fn test_exif_from_heif_rejects_other_brands() {
+        let item = b"\0\0\0\0MM\0*".to_vec();
+        let data = heif_with_item(b"isom", b"Exif\0", &item, true);
+        assert_eq!(exif_from_heif(&mut Cursor::new(data)), None);
+    }
+
+    #[test]
+    fn test_xmp_from_heif() {
+        let packet = b"<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/>";
+        let data = heif_with_item(b"heic", b"mime\0application/rdf+xml\0", packet, false);
+        assert_eq!(xmp_from_heif(&mut Cursor::new(data)), Some(packet.to_vec()));
+        let data = heif_with_item(b"heic", b"mime\0image/jpeg\0", packet, false);
+        assert_eq!(xmp_from_heif(&mut Cursor::new(data)), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! Minimal ISO base media file format (ISOBMFF) box reading, as used by HEIF/AVIF
+//! images, Canon CR3 raw files and MP4/QuickTime video.
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// A box found in a file: its type, and where its body starts and how long it is.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct BoxRef {
+    pub box_type: [u8; 4],
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// Lists the top-level boxes of a file, seeking over their bodies.
+pub(crate) fn top_level_boxes<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<BoxRef>> {
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    let mut ret = Vec::new();
+    let mut pos = 0;
+    while pos + 8 <= file_size {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        let (size, header_size) = match u32::from_be_bytes(header[0..4].try_into().unwrap()) {
+            0 => (file_size - pos, 8),
+            1 => {
+                let mut large = [0; 8];
+                reader.read_exact(&mut large)?;
+                (u64::from_be_bytes(large), 16)
+            }
+            size => (size as u64, 8),
+        };
+        if size < header_size || pos.checked_add(size).is_none_or(|end| end > file_size) {
+            break; // Truncated or broken; keep what we have
+        }
+        ret.push(BoxRef {
+            box_type: header[4..8].try_into().unwrap(),
+            offset: pos + header_size,
+            size: size - header_size,
+        });
+        pos += size;
+        reader.seek(SeekFrom::Start(pos))?;
+    }
+    Ok(ret)
+}
+
+/// Reads the body of a box, refusing bodies larger than `max_size`.
+pub(crate) fn read_box_body<R: Read + Seek>(
+    reader: &mut R,
+    b: &BoxRef,
+    max_size: u64,
+) -> io::Result<Vec<u8>> {
+    read_at(reader, b.offset, b.size, max_size)
+}
+
+pub(crate) fn read_at<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    size: u64,
+    max_size: u64,
+) -> io::Result<Vec<u8>> {
+    if size > max_size {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "box too large"));
+    }
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut buf = vec![0; size as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Splits an in-memory box body into its child boxes. Stops at the first malformed box.
+pub(crate) fn child_boxes(data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut ret = Vec::new();
+    let mut r = ByteReader::new(data);
+    while r.remaining() >= 8 {
+        let (Some(size), Some(box_type)) = (r.u32(), r.array4()) else {
+            break;
+        };
+        let (size, header_size) = match size {
+            0 => (r.remaining() as u64 + 8, 8),
+            1 => match r.u64() {
+                Some(size) => (size, 16),
+                None => break,
+            },
+            size => (size as u64, 8),
+        };
+        let Some(body) = size
+            .checked_sub(header_size)
+            .and_then(|len| r.take(len as usize))
+        else {
+            break;
+        };
+        ret.push((box_type, body));
+    }
+    ret
+}
+
+pub(crate) fn find_child<'a>(data: &'a [u8], box_type: &[u8; 4]) -> Option<&'a [u8]> {
+    child_boxes(data)
+        .into_iter()
+        .find(|(t, _)| t == box_type)
+        .map(|(_, body)| body)
+}
+
+/// Big-endian reader over a byte slice; every read returns `None` past the end.
+pub(crate) struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    pub fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+
+    pub fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(len)?;
+        let ret = self.data.get(self.pos..end)?;
+        self.pos = end;
+        Some(ret)
+    }
+
+    pub fn skip(&mut self, len: usize) -> Option<()> {
+        self.take(len).map(|_| ())
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_be_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_be_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    pub fn array4(&mut self) -> Option<[u8; 4]> {
+        self.take(4)?.try_into().ok()
+    }
+
+    /// Reads an unsigned integer of 0, 4 or 8 bytes, as used for ISOBMFF offset fields.
+    pub fn sized(&mut self, size: usize) -> Option<u64> {
+        match size {
+            0 => Some(0),
+            4 => self.u32().map(u64::from),
+            8 => self.u64(),
+            _ => None,
+        }
+    }
+
+    /// Reads the version and flags of a "full box" header.
+    pub fn full_box_header(&mut self) -> Option<(u8, u32)> {
+        let v = self.u32()?;
+        Some(((v >> 24) as u8, v & 0x00ff_ffff))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Builds a box from its type and body, for tests here and in the container readers.
+    pub(crate) fn make_box(box_type: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut ret = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        ret.extend_from_slice(box_type);
+        ret.extend_from_slice(body);
+        ret
+    }
+
+    #[test]
+    fn test_top_level_boxes() {
+        let mut data = make_box(b"ftyp", b"heic\0\0\0\0mif1");
+        data.extend(make_box(b"free", b""));
+        data.extend(make_box(b"mdat", b"12345"));
+        let boxes = top_level_boxes(&mut io::Cursor::new(&data)).unwrap();
+        let types: Vec<&[u8; 4]> = boxes.iter().map(|b| &b.box_type).collect();
+        assert_eq!(types, vec![b"ftyp", b"free", b"mdat"]);
+        assert_eq!(boxes[2].size, 5);
+        let body = read_box_body(&mut io::Cursor::new(&data), &boxes[2], 100).unwrap();
+        assert_eq!(body, b"12345");
+    }
+
+    #[test]
+    fn test_truncated_box_is_ignored() {
+        let mut data = make_box(b"ftyp", b"heic");
+        data.extend(&[0, 0, 1, 0, b'm', b'd', b'a', b't', 1, 2]);
+        let boxes = top_level_boxes(&mut io::Cursor::new(&data)).unwrap();
+        assert_eq!(boxes.len(), 1);
+    }
+}
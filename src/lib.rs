@@ -1,2 +1,22 @@
+pub mod camera_info;
+pub mod config;
+mod date_time;
+mod datum;
+pub mod description;
+mod dji_srt;
+mod exiftool;
 pub mod file_location;
 pub mod file_set;
+mod file_type;
+mod gpmf;
+pub mod gps_accuracy;
+mod heif;
+mod isobmff;
+mod lightroom;
+pub mod object_location;
+mod quicktime;
+mod raw;
+mod takeout;
+pub mod track;
+pub mod validation;
+mod xmp;
@@ -0,0 +1,131 @@
+//! Lightroom Classic catalogs (`.lrcat`), which are SQLite databases. Positions added
+//! in Lightroom are often only in the catalog, in `AgHarvestedExifMetadata`; the file
+//! path is put together from the file's folder and that folder's root folder.
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use rusqlite::{Connection, OpenFlags};
+
+/// Virtual copies (`masterImage` set) share the file of their master and are left out.
+const QUERY: &str = "SELECT root.absolutePath, folder.pathFromRoot, file.baseName,
+        file.extension, image.captureTime, exif.gpsLatitude, exif.gpsLongitude
+    FROM AgHarvestedExifMetadata exif
+    JOIN Adobe_images image ON image.id_local = exif.image
+    JOIN AgLibraryFile file ON file.id_local = image.rootFile
+    JOIN AgLibraryFolder folder ON folder.id_local = file.folder
+    JOIN AgLibraryRootFolder root ON root.id_local = folder.rootFolder
+    WHERE exif.hasGPS = 1 AND exif.gpsLatitude IS NOT NULL
+        AND exif.gpsLongitude IS NOT NULL AND image.masterImage IS NULL";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct CatalogImage {
+    pub file: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Local capture time, with its UTC offset if Lightroom knows it
+    pub capture_time: Option<NaiveDateTime>,
+    pub offset: Option<FixedOffset>,
+}
+
+/// Reads the geotagged images of a catalog. The catalog is opened read-only, and
+/// cannot be read while Lightroom has it open.
+pub(crate) fn images_from_catalog(path: &str) -> Result<Vec<CatalogImage>> {
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    let db = Connection::open_with_flags(path, flags)
+        .with_context(|| format!("Cannot open catalog '{path}'"))?;
+    let mut statement = db
+        .prepare(QUERY)
+        .with_context(|| format!("'{path}' is not a Lightroom catalog, or is in use"))?;
+    let rows = statement.query_map([], |row| {
+        let root: String = row.get(0)?;
+        let folder: String = row.get(1)?;
+        let base_name: String = row.get(2)?;
+        let extension: Option<String> = row.get(3)?;
+        let capture_time: Option<String> = row.get(4)?;
+        let file = match extension.filter(|e| !e.is_empty()) {
+            Some(extension) => format!("{root}{folder}{base_name}.{extension}"),
+            None => format!("{root}{folder}{base_name}"),
+        };
+        let (capture_time, offset) = capture_time.as_deref().and_then(parse_capture_time).unzip();
+        Ok(CatalogImage {
+            file,
+            latitude: row.get(5)?,
+            longitude: row.get(6)?,
+            capture_time,
+            offset: offset.flatten(),
+        })
+    })?;
+    rows.collect::<rusqlite::Result<_>>()
+        .with_context(|| format!("Failed to read catalog '{path}'"))
+}
+
+/// Lightroom writes eg "2025-03-06T05:41:42.125", with "+01:00" appended if the
+/// offset is known.
+fn parse_capture_time(s: &str) -> Option<(NaiveDateTime, Option<FixedOffset>)> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some((dt.naive_local(), Some(*dt.offset())));
+    }
+    let local = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f").ok()?;
+    Some((local, None))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Writes a catalog with the tables and columns that are read, with one image with
+    /// a position and offset, a virtual copy of it, one without an offset and one
+    /// without a position.
+    pub(crate) fn make_catalog(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_file(&path);
+        let db = Connection::open(&path).unwrap();
+        db.execute_batch(
+            "CREATE TABLE AgLibraryRootFolder (id_local INTEGER PRIMARY KEY, absolutePath TEXT);
+            CREATE TABLE AgLibraryFolder (id_local INTEGER PRIMARY KEY, rootFolder INTEGER,
+                pathFromRoot TEXT);
+            CREATE TABLE AgLibraryFile (id_local INTEGER PRIMARY KEY, folder INTEGER,
+                baseName TEXT, extension TEXT);
+            CREATE TABLE Adobe_images (id_local INTEGER PRIMARY KEY, rootFile INTEGER,
+                captureTime TEXT, masterImage INTEGER);
+            CREATE TABLE AgHarvestedExifMetadata (id_local INTEGER PRIMARY KEY,
+                image INTEGER, hasGPS INTEGER, gpsLatitude REAL, gpsLongitude REAL);
+            INSERT INTO AgLibraryRootFolder VALUES (1, '/photos/');
+            INSERT INTO AgLibraryFolder VALUES (10, 1, '2025/03/');
+            INSERT INTO AgLibraryFile VALUES (100, 10, 'sunrise', 'CR2'),
+                (101, 10, 'noon', 'jpg'), (102, 10, 'indoors', 'jpg');
+            INSERT INTO Adobe_images VALUES (1000, 100, '2025-03-06T05:41:42.125+01:00', NULL),
+                (1001, 100, '2025-03-06T05:41:42.125+01:00', 1000),
+                (1002, 101, '2025-03-06T12:00:00', NULL), (1003, 102, NULL, NULL);
+            INSERT INTO AgHarvestedExifMetadata VALUES (1, 1000, 1, 45.505667, 12.341161),
+                (2, 1001, 1, 45.505667, 12.341161), (3, 1002, 1, 45.4343, 12.3388),
+                (4, 1003, 0, NULL, NULL);",
+        )
+        .unwrap();
+        path
+    }
+
+    #[test]
+    fn test_images_from_catalog() {
+        let path = make_catalog("img_coords_test_images.lrcat");
+        let mut images = images_from_catalog(path.to_str().unwrap()).unwrap();
+        images.sort_by(|a, b| a.file.cmp(&b.file));
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].file, "/photos/2025/03/noon.jpg");
+        assert_eq!(images[0].offset, None);
+        assert_eq!(images[1].file, "/photos/2025/03/sunrise.CR2");
+        assert_eq!(images[1].latitude, 45.505667);
+        assert_eq!(
+            images[1].capture_time.unwrap().to_string(),
+            "2025-03-06 05:41:42.125"
+        );
+        assert_eq!(images[1].offset.unwrap().to_string(), "+01:00");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_not_a_catalog() {
+        assert!(images_from_catalog("test_files/sunrise.jpg").is_err());
+        assert!(images_from_catalog("/nonexistent.lrcat").is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! The position of the photographed object, from the EXIF `GPSDest*` tags, as opposed to
+//! the camera position. With sight lines on, it is written as its own feature/placemark
+//! with the object point and a line from the camera to it, under the image's name.
+use crate::file_location::{
+    DirectionRef, property_direction_ref, property_f64, push_property, xml_escaped,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, json};
+
+const EARTH_RADIUS_METRES: f64 = 6_371_008.8;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ObjectLocation {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub bearing: Option<f64>, // Degrees from the camera to the object
+    pub bearing_ref: Option<DirectionRef>,
+    pub distance: Option<f64>, // Metres from the camera
+}
+
+impl ObjectLocation {
+    /// The point `distance` metres from the camera along `bearing`, on a sphere. Only
+    /// for true bearings, as magnetic ones would need the local declination.
+    pub(crate) fn from_bearing(
+        camera_latitude: f64,
+        camera_longitude: f64,
+        bearing: f64,
+        bearing_ref: Option<DirectionRef>,
+        distance: f64,
+    ) -> Option<Self> {
+        if bearing_ref == Some(DirectionRef::Magnetic) || !distance.is_finite() {
+            return None;
+        }
+        let (lat, lon) = (camera_latitude.to_radians(), camera_longitude.to_radians());
+        let angle = distance / EARTH_RADIUS_METRES;
+        let bearing_rad = bearing.to_radians();
+        let latitude =
+            (lat.sin() * angle.cos() + lat.cos() * angle.sin() * bearing_rad.cos()).asin();
+        let longitude = lon
+            + (bearing_rad.sin() * angle.sin() * lat.cos())
+                .atan2(angle.cos() - lat.sin() * latitude.sin());
+        Some(Self {
+            latitude: latitude.to_degrees(),
+            longitude: (longitude.to_degrees() + 540.0).rem_euclid(360.0) - 180.0,
+            bearing: Some(bearing),
+            bearing_ref,
+            distance: Some(distance),
+        })
+    }
+
+    /// `object_*` properties of the camera point, so that the object is read back with
+    /// the image even though the sight line is a feature of its own.
+    pub(crate) fn properties(&self) -> Vec<(&'static str, serde_json::Value)> {
+        let mut ret = vec![
+            ("object_latitude", json!(self.latitude)),
+            ("object_longitude", json!(self.longitude)),
+        ];
+        push_property(&mut ret, "object_bearing", self.bearing);
+        push_property(
+            &mut ret,
+            "object_bearing_ref",
+            self.bearing_ref.map(|r| r.as_str()),
+        );
+        push_property(&mut ret, "object_distance", self.distance);
+        ret
+    }
+
+    pub(crate) fn from_properties(properties: &Map<String, serde_json::Value>) -> Option<Self> {
+        Some(Self {
+            latitude: property_f64(properties, "object_latitude")?,
+            longitude: property_f64(properties, "object_longitude")?,
+            bearing: property_f64(properties, "object_bearing"),
+            bearing_ref: property_direction_ref(properties, "object_bearing_ref"),
+            distance: property_f64(properties, "object_distance"),
+        })
+    }
+
+    /// A feature with the object point and the sight line from the camera. Being a
+    /// GeometryCollection, it is not mistaken for an image or a track when loaded again.
+    pub fn as_geojson(&self, name: &str, camera: (f64, f64)) -> String {
+        let (latitude, longitude) = camera;
+        json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "GeometryCollection",
+                "geometries": [
+                    {
+                        "type": "Point",
+                        "coordinates": [self.longitude, self.latitude]
+                    },
+                    {
+                        "type": "LineString",
+                        "coordinates": [[longitude, latitude], [self.longitude, self.latitude]]
+                    }
+                ]
+            },
+            "properties": {
+                "name": name,
+                "sight_line": true,
+            }
+        })
+        .to_string()
+    }
+
+    pub fn as_kml(&self, name: &str, camera: (f64, f64)) -> String {
+        let (latitude, longitude) = camera;
+        format!(
+            "<Placemark><name>{}</name><MultiGeometry><Point><coordinates>{},{}</coordinates></Point><LineString><coordinates>{longitude},{latitude} {},{}</coordinates></LineString></MultiGeometry></Placemark>",
+            xml_escaped(name),
+            self.longitude,
+            self.latitude,
+            self.longitude,
+            self.latitude,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bearing() {
+        // 1 km due east and due north of a point on the equator
+        let east = ObjectLocation::from_bearing(0.0, 0.0, 90.0, None, 1000.0).unwrap();
+        assert!(east.latitude.abs() < 1e-9);
+        assert!((east.longitude - 0.008993).abs() < 1e-6);
+        let north =
+            ObjectLocation::from_bearing(0.0, 179.999, 0.0, Some(DirectionRef::True), 1000.0)
+                .unwrap();
+        assert!((north.latitude - 0.008993).abs() < 1e-6);
+        assert!((north.longitude - 179.999).abs() < 1e-9);
+        // Across the antimeridian
+        let west = ObjectLocation::from_bearing(0.0, -179.999, 270.0, None, 1000.0).unwrap();
+        assert!(west.longitude > 179.0);
+        assert_eq!(
+            ObjectLocation::from_bearing(0.0, 0.0, 90.0, Some(DirectionRef::Magnetic), 1000.0),
+            None
+        );
+    }
+
+    /// Both coordinates are needed; the rest is optional
+    #[test]
+    fn test_from_properties() {
+        let properties = json!({
+            "object_latitude": "45.4341",
+            "object_longitude": 12.3397,
+            "object_bearing_ref": "",
+        });
+        let object = ObjectLocation::from_properties(properties.as_object().unwrap()).unwrap();
+        assert_eq!((object.latitude, object.longitude), (45.4341, 12.3397));
+        assert_eq!(object.bearing_ref, None);
+        let properties = json!({"object_latitude": 45.4341, "object_bearing": 90});
+        assert_eq!(
+            ObjectLocation::from_properties(properties.as_object().unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_as_geojson_and_kml() {
+        let object = ObjectLocation {
+            latitude: 45.4341,
+            longitude: 12.3397,
+            ..Default::default()
+        };
+        let v: serde_json::Value =
+            serde_json::from_str(&object.as_geojson("a.jpg", (45.4336, 12.3388))).unwrap();
+        assert_eq!(
+            v["geometry"]["geometries"][0]["coordinates"],
+            json!([12.3397, 45.4341])
+        );
+        assert_eq!(
+            v["geometry"]["geometries"][1]["coordinates"],
+            json!([[12.3388, 45.4336], [12.3397, 45.4341]])
+        );
+        assert_eq!(
+            object.as_kml("a & b.jpg", (45.4336, 12.3388)),
+            "<Placemark><name>a &amp; b.jpg</name><MultiGeometry><Point><coordinates>12.3397,45.4341</coordinates></Point><LineString><coordinates>12.3388,45.4336 12.3397,45.4341</coordinates></LineString></MultiGeometry></Placemark>"
+        );
+    }
+}
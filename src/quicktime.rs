@@ -0,0 +1,212 @@
+//! Location and creation time of QuickTime/MP4 videos, as written by phones: an ISO 6709
+//! string in the `©xyz` user data atom or the `com.apple.quicktime.location.ISO6709`
+//! metadata key, and the creation date from the metadata keys or the movie header.
+use crate::isobmff::{self, ByteReader};
+use chrono::{DateTime, FixedOffset, TimeDelta, Utc};
+use regex::Regex;
+use std::io::{Read, Seek};
+use std::sync::LazyLock;
+
+const MAX_MOOV_SIZE: u64 = 64 * 1024 * 1024;
+const KEY_LOCATION: &str = "com.apple.quicktime.location.ISO6709";
+const KEY_CREATION_DATE: &str = "com.apple.quicktime.creationdate";
+/// Seconds from the QuickTime epoch (1904-01-01) to the Unix epoch
+const QUICKTIME_EPOCH_OFFSET: i64 = 2_082_844_800;
+
+/// Latitude, longitude and optional altitude, each with a sign and any number of
+/// integer and fraction digits.
+static RE_ISO6709: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^([+-]\d+(?:\.\d+)?)([+-]\d+(?:\.\d+)?)([+-]\d+(?:\.\d+)?)?")
+        .expect("re_iso6709 does not compile")
+});
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct VideoMetadata {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+    /// Local creation time, from the Apple metadata keys
+    pub creation_date: Option<DateTime<FixedOffset>>,
+    /// From the movie header, which is UTC
+    pub creation_time: Option<DateTime<Utc>>,
+}
+
+/// Reads the position of a QuickTime/MP4 video. Returns `None` if it has none.
+pub(crate) fn metadata_from_video<R: Read + Seek>(reader: &mut R) -> Option<VideoMetadata> {
+    let boxes = isobmff::top_level_boxes(reader).ok()?;
+    let moov = boxes.iter().find(|b| &b.box_type == b"moov")?;
+    let moov = isobmff::read_box_body(reader, moov, MAX_MOOV_SIZE).ok()?;
+
+    let keys = isobmff::find_child(&moov, b"meta")
+        .map(metadata_keys)
+        .unwrap_or_default();
+    let key = |name: &str| {
+        keys.iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    };
+    let udta_xyz = isobmff::find_child(&moov, b"udta")
+        .and_then(|udta| isobmff::find_child(udta, b"\xa9xyz"))
+        .and_then(user_data_string);
+    let (latitude, longitude, altitude) = key(KEY_LOCATION)
+        .or(udta_xyz.as_deref())
+        .and_then(parse_iso6709)?;
+
+    Some(VideoMetadata {
+        latitude,
+        longitude,
+        altitude,
+        creation_date: key(KEY_CREATION_DATE).and_then(parse_creation_date),
+        creation_time: isobmff::find_child(&moov, b"mvhd").and_then(movie_creation_time),
+    })
+}
+
+/// The `keys` and `ilst` boxes of a QuickTime `meta` box, as (key, string value) pairs.
+fn metadata_keys(meta: &[u8]) -> Vec<(String, String)> {
+    // In QuickTime files `meta` is a plain box, in MP4 a full box with version and flags
+    let meta = match meta.get(4..8) {
+        Some(b"hdlr") => meta,
+        _ => meta.get(4..).unwrap_or_default(),
+    };
+    let (Some(keys), Some(ilst)) = (
+        isobmff::find_child(meta, b"keys"),
+        isobmff::find_child(meta, b"ilst"),
+    ) else {
+        return vec![];
+    };
+    let mut r = ByteReader::new(keys);
+    let mut names = vec![];
+    if r.full_box_header().is_some()
+        && let Some(count) = r.u32()
+    {
+        for _ in 0..count {
+            let Some(entry) = r
+                .u32()
+                .and_then(|size| r.take((size as usize).saturating_sub(4)))
+            else {
+                break;
+            };
+            // Namespace, usually "mdta", then the key name
+            names.push(String::from_utf8_lossy(entry.get(4..).unwrap_or_default()).to_string());
+        }
+    }
+    isobmff::child_boxes(ilst)
+        .into_iter()
+        .filter_map(|(index, item)| {
+            // Items are numbered from 1 by their key index
+            let name = names.get((u32::from_be_bytes(index) as usize).checked_sub(1)?)?;
+            let mut r = ByteReader::new(isobmff::find_child(item, b"data")?);
+            let (_type, _locale) = (r.u32()?, r.u32()?);
+            let value = String::from_utf8(r.rest().to_vec()).ok()?;
+            Some((name.clone(), value))
+        })
+        .collect()
+}
+
+/// A user data text atom: string length, language code, then the string.
+fn user_data_string(atom: &[u8]) -> Option<String> {
+    let mut r = ByteReader::new(atom);
+    let length = r.u16()? as usize;
+    let _language = r.u16()?;
+    String::from_utf8(r.take(length)?.to_vec()).ok()
+}
+
+/// Parses an ISO 6709 position such as "+45.5057+012.3412+046.790/". Degrees may also
+/// be written as DDMM.MM or DDMMSS.SS, told apart by the number of integer digits.
+pub(crate) fn parse_iso6709(s: &str) -> Option<(f64, f64, Option<f64>)> {
+    let captures = RE_ISO6709.captures(s.trim())?;
+    let latitude = iso6709_degrees(captures.get(1)?.as_str(), 2)?;
+    let longitude = iso6709_degrees(captures.get(2)?.as_str(), 3)?;
+    let altitude = captures.get(3).and_then(|m| m.as_str().parse().ok());
+    Some((latitude, longitude, altitude))
+}
+
+fn iso6709_degrees(s: &str, degree_digits: usize) -> Option<f64> {
+    let (sign, digits) = s.split_at(1);
+    let sign = if sign == "-" { -1.0 } else { 1.0 };
+    let int_digits = digits.find('.').unwrap_or(digits.len());
+    let value: f64 = digits.parse().ok()?;
+    let degrees = match int_digits.checked_sub(degree_digits)? {
+        0 => value,
+        2 => (value / 100.0).trunc() + (value % 100.0) / 60.0,
+        4 => {
+            let (d, rest) = ((value / 10000.0).trunc(), value % 10000.0);
+            d + (rest / 100.0).trunc() / 60.0 + (rest % 100.0) / 3600.0
+        }
+        _ => return None,
+    };
+    Some(sign * degrees)
+}
+
+/// Apple writes eg "2025-03-06T05:41:42+0100", without the colon RFC 3339 needs.
+fn parse_creation_date(s: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(s)
+        .or_else(|_| DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%z"))
+        .ok()
+}
+
+/// The creation time in the `mvhd` box. Zero means it was never set.
+fn movie_creation_time(mvhd: &[u8]) -> Option<DateTime<Utc>> {
+    let mut r = ByteReader::new(mvhd);
+    let (version, _) = r.full_box_header()?;
+    let seconds = match version {
+        1 => r.u64()?,
+        _ => r.u32()? as u64,
+    };
+    if seconds == 0 {
+        return None;
+    }
+    let since_unix_epoch = i64::try_from(seconds).ok()? - QUICKTIME_EPOCH_OFFSET;
+    DateTime::UNIX_EPOCH.checked_add_signed(TimeDelta::try_seconds(since_unix_epoch)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isobmff::tests::make_box;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_parse_iso6709() {
+        assert_eq!(
+            parse_iso6709("+45.5057+012.3412+046.790/"),
+            Some((45.5057, 12.3412, Some(46.79)))
+        );
+        assert_eq!(
+            parse_iso6709("-33.8688+151.2093/"),
+            Some((-33.8688, 151.2093, None))
+        );
+        // DDMM.MM and DDDMMSS
+        let (lat, lon, _) = parse_iso6709("+4530.34-0122028/").unwrap();
+        assert!((lat - 45.505666).abs() < 1e-6);
+        assert!((lon + 12.341111).abs() < 1e-6);
+        assert_eq!(parse_iso6709("nowhere"), None);
+    }
+
+    #[test]
+    fn test_user_data_location() {
+        let mut xyz = vec![0, 18, 0x15, 0xc7];
+        xyz.extend(b"+45.5057+012.3412/");
+        let mut mvhd = vec![0; 4];
+        mvhd.extend(((1_741_239_702 + QUICKTIME_EPOCH_OFFSET) as u32).to_be_bytes());
+        let mut moov = make_box(b"mvhd", &mvhd);
+        moov.extend(make_box(b"udta", &make_box(b"\xa9xyz", &xyz)));
+        let mut data = make_box(b"ftyp", b"isom\0\0\0\0isom");
+        data.extend(make_box(b"moov", &moov));
+
+        let video = metadata_from_video(&mut Cursor::new(data)).unwrap();
+        assert_eq!((video.latitude, video.longitude), (45.5057, 12.3412));
+        assert_eq!(video.creation_date, None);
+        assert_eq!(
+            video.creation_time.unwrap().to_rfc3339(),
+            "2025-03-06T05:41:42+00:00"
+        );
+    }
+
+    #[test]
+    fn test_no_location() {
+        let mut data = make_box(b"ftyp", b"qt  \0\0\0\0qt  ");
+        data.extend(make_box(b"moov", &make_box(b"mvhd", &[0; 8])));
+        assert_eq!(metadata_from_video(&mut Cursor::new(data)), None);
+    }
+}
@@ -0,0 +1,128 @@
+//! EXIF extraction for camera raw formats the EXIF crate cannot open directly. Most raw
+//! formats (DNG, CR2, NEF, ARW) are plain TIFF and need nothing from here; Olympus ORF
+//! and Panasonic RW2 are TIFF with a non-standard magic number, Canon CR3 is ISOBMFF
+//! with the TIFF IFDs split into separate boxes, and Fujifilm RAF wraps a JPEG preview
+//! that carries the EXIF block.
+use crate::isobmff::{self, ByteReader};
+use exif::{Context, Field, In, Tag, Value};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+/// The `uuid` box in a CR3 `moov` box that holds the CMT1..CMT4 metadata boxes.
+const CR3_METADATA_UUID: [u8; 16] = [
+    0x85, 0xc0, 0xb6, 0x87, 0x82, 0x0f, 0x11, 0xe0, 0x81, 0x11, 0xf4, 0xce, 0x46, 0x2b, 0x6a, 0x48,
+];
+const RAF_MAGIC: &[u8] = b"FUJIFILMCCD-RAW ";
+const MAX_BOX_SIZE: u64 = 64 * 1024 * 1024;
+const MAX_PREVIEW_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Returns the TIFF-format EXIF block of a raw file, if it is one of the formats above.
+pub(crate) fn exif_from_raw<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    let mut head = Vec::new();
+    reader.seek(SeekFrom::Start(0)).ok()?;
+    reader.by_ref().take(16).read_to_end(&mut head).ok()?;
+    match head.as_slice() {
+        [b'I', b'I', b'R', b'O' | b'S', ..] | [b'I', b'I', b'U', 0, ..] => {
+            tiff_with_standard_magic(reader, [b'I', b'I', 42, 0])
+        }
+        [b'M', b'M', b'O', b'R', ..] => tiff_with_standard_magic(reader, [b'M', b'M', 0, 42]),
+        [
+            _,
+            _,
+            _,
+            _,
+            b'f',
+            b't',
+            b'y',
+            b'p',
+            b'c',
+            b'r',
+            b'x',
+            b' ',
+            ..,
+        ] => exif_from_cr3(reader),
+        _ if head.starts_with(RAF_MAGIC) => exif_from_raf(reader),
+        _ => None,
+    }
+}
+
+/// ORF and RW2 files are TIFF apart from the magic number, which the EXIF crate rejects.
+fn tiff_with_standard_magic<R: Read + Seek>(reader: &mut R, magic: [u8; 4]) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    reader.seek(SeekFrom::Start(0)).ok()?;
+    reader.read_to_end(&mut data).ok()?;
+    data.get_mut(0..4)?.copy_from_slice(&magic);
+    Some(data)
+}
+
+/// RAF: the header points to an embedded JPEG preview, whose APP1 segment has the EXIF.
+fn exif_from_raf<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    let header = isobmff::read_at(reader, 84, 8, 8).ok()?;
+    let mut r = ByteReader::new(&header);
+    let offset = r.u32()? as u64;
+    let length = r.u32()? as u64;
+    let jpeg = isobmff::read_at(reader, offset, length, MAX_PREVIEW_SIZE).ok()?;
+    exif::get_exif_attr_from_jpeg(&mut Cursor::new(jpeg)).ok()
+}
+
+/// CR3: CMT1 holds IFD0, CMT2 the Exif IFD and CMT4 the GPS IFD, each as a separate
+/// TIFF structure. They are merged into a single TIFF block with the usual layout.
+fn exif_from_cr3<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    let boxes = isobmff::top_level_boxes(reader).ok()?;
+    let moov = boxes.iter().find(|b| &b.box_type == b"moov")?;
+    let moov = isobmff::read_box_body(reader, moov, MAX_BOX_SIZE).ok()?;
+    let metadata = isobmff::child_boxes(&moov)
+        .into_iter()
+        .filter(|(t, _)| t == b"uuid")
+        .find_map(|(_, body)| body.strip_prefix(&CR3_METADATA_UUID[..]))?;
+
+    let mut fields = Vec::new();
+    for (box_type, body) in isobmff::child_boxes(metadata) {
+        let context = match &box_type {
+            b"CMT1" => Context::Tiff,
+            b"CMT2" => Context::Exif,
+            b"CMT4" => Context::Gps,
+            _ => continue,
+        };
+        let Ok(exif) = exif::Reader::new().read_raw(body.to_vec()) else {
+            continue;
+        };
+        // Each box is read as if it were IFD0, so the tags need their real context back.
+        fields.extend(
+            exif.fields()
+                .filter(|f| f.ifd_num == In::PRIMARY)
+                .filter(|f| !matches!(f.value, Value::Unknown(..)))
+                .map(|f| Field {
+                    tag: Tag(context, f.tag.number()),
+                    ifd_num: In::PRIMARY,
+                    value: f.value.clone(),
+                }),
+        );
+    }
+    let mut writer = exif::experimental::Writer::new();
+    for field in &fields {
+        writer.push_field(field);
+    }
+    let mut buf = Cursor::new(Vec::new());
+    writer.write(&mut buf, false).ok()?;
+    Some(buf.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_format() {
+        let mut data = Cursor::new(b"just some text, not a raw file".to_vec());
+        assert_eq!(exif_from_raw(&mut data), None);
+        assert_eq!(exif_from_raw(&mut Cursor::new(vec![])), None);
+    }
+
+    #[test]
+    fn test_orf_magic_is_replaced() {
+        let mut data = Cursor::new(b"IIRO\x08\0\0\0rest".to_vec());
+        let tiff = exif_from_raw(&mut data).unwrap();
+        assert_eq!(&tiff[..4], b"II*\0");
+        assert_eq!(&tiff[4..], b"\x08\0\0\0rest");
+    }
+}
@@ -0,0 +1,146 @@
+//! Google Photos Takeout sidecars: the `IMG_1234.jpg.json` (or, in newer exports,
+//! `IMG_1234.jpg.supplemental-metadata.json`) files Takeout writes next to each photo,
+//! often after stripping the GPS tags from the photo itself.
+//!
+//! Takeout cuts long sidecar names short, moves the `(1)` of duplicate photos behind
+//! the image extension, and shares the sidecar of a photo with its `-edited` copy.
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Takeout keeps at most this many characters of the sidecar name before ".json"
+const MAX_NAME_LENGTH: usize = 46;
+const SUPPLEMENTAL_SUFFIX: &str = ".supplemental-metadata";
+
+/// The stem of a duplicate ("IMG_1234(1)") or edited ("IMG_1234-edited") photo.
+static RE_STEM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.*?)(-edited)?(\(\d+\))?$").expect("re_stem does not compile"));
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct TakeoutMetadata {
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub altitude: Option<f64>,
+    pub taken: Option<DateTime<Utc>>,
+}
+
+impl TakeoutMetadata {
+    /// Parses a Takeout sidecar. The position is taken from `geoData`, which includes
+    /// edits made in Google Photos, else from `geoDataExif`; both are 0, 0 if unknown.
+    pub(crate) fn parse(json: &str) -> Option<Self> {
+        let v: serde_json::Value = serde_json::from_str(json).ok()?;
+        let position = ["geoData", "geoDataExif"].iter().find_map(|key| {
+            let geo = &v[key];
+            let latitude = geo["latitude"].as_f64()?;
+            let longitude = geo["longitude"].as_f64()?;
+            (latitude != 0.0 || longitude != 0.0).then(|| {
+                let altitude = geo["altitude"].as_f64().filter(|a| *a != 0.0);
+                (latitude, longitude, altitude)
+            })
+        });
+        // Seconds since the Unix epoch, as a string
+        let taken = v["photoTakenTime"]["timestamp"]
+            .as_str()
+            .and_then(|s| s.parse::<i64>().ok())
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0));
+        Some(Self {
+            latitude: position.map(|(latitude, _, _)| latitude),
+            longitude: position.map(|(_, longitude, _)| longitude),
+            altitude: position.and_then(|(_, _, altitude)| altitude),
+            taken,
+        })
+    }
+}
+
+/// Finds the Takeout sidecar of an image, if there is one.
+pub(crate) fn sidecar_path(file_name: &str) -> Option<PathBuf> {
+    sidecar_candidates(Path::new(file_name))
+        .into_iter()
+        .find(|p| p.is_file())
+}
+
+/// Possible sidecar names, longest first: the image name with all or part of the
+/// ".supplemental-metadata" suffix, truncated to `MAX_NAME_LENGTH`, then the stem alone.
+fn sidecar_candidates(path: &Path) -> Vec<PathBuf> {
+    let (Some(stem), Some(dir)) = (path.file_stem().and_then(|s| s.to_str()), path.parent()) else {
+        return vec![];
+    };
+    let captures = RE_STEM.captures(stem);
+    let group = |i| {
+        captures
+            .as_ref()
+            .and_then(|c| c.get(i))
+            .map_or("", |m| m.as_str())
+    };
+    let (base, counter) = (group(1), group(3));
+    let full = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{base}.{ext}"),
+        None => base.to_string(),
+    };
+    let long = format!("{full}{SUPPLEMENTAL_SUFFIX}");
+    let shortest = full.len().min(MAX_NAME_LENGTH);
+    let longest = long.len().min(MAX_NAME_LENGTH.max(full.len()));
+    let mut names: Vec<&str> = (shortest..=longest)
+        .rev()
+        .filter_map(|len| long.get(..len))
+        .collect();
+    names.push(base);
+    names
+        .into_iter()
+        .map(|name| dir.join(format!("{name}{counter}.json")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIDECAR: &str = r#"{
+      "title": "IMG_1234.jpg",
+      "photoTakenTime": {"timestamp": "1741239702", "formatted": "Mar 6, 2025, 5:41:42 AM UTC"},
+      "geoData": {"latitude": 0.0, "longitude": 0.0, "altitude": 0.0},
+      "geoDataExif": {"latitude": 45.5056667, "longitude": 12.3411611, "altitude": 46.79}
+    }"#;
+
+    #[test]
+    fn test_parse() {
+        let takeout = TakeoutMetadata::parse(SIDECAR).unwrap();
+        assert_eq!(takeout.latitude, Some(45.5056667));
+        assert_eq!(takeout.longitude, Some(12.3411611));
+        assert_eq!(takeout.altitude, Some(46.79));
+        assert_eq!(
+            takeout.taken.unwrap().to_rfc3339(),
+            "2025-03-06T05:41:42+00:00"
+        );
+        let no_position = SIDECAR
+            .replace("45.5056667", "0.0")
+            .replace("12.3411611", "0.0");
+        assert_eq!(TakeoutMetadata::parse(&no_position).unwrap().latitude, None);
+        assert_eq!(TakeoutMetadata::parse("not json"), None);
+    }
+
+    #[test]
+    fn test_sidecar_candidates() {
+        let names = |file: &str| -> Vec<String> {
+            sidecar_candidates(Path::new(file))
+                .iter()
+                .map(|p| p.to_str().unwrap().to_string())
+                .collect()
+        };
+        let plain = names("a/IMG_1234.jpg");
+        assert_eq!(plain[0], "a/IMG_1234.jpg.supplemental-metadata.json");
+        assert!(plain.contains(&"a/IMG_1234.jpg.supplemental-metad.json".to_string()));
+        assert!(plain.contains(&"a/IMG_1234.jpg.json".to_string()));
+        assert_eq!(plain.last().unwrap(), "a/IMG_1234.json");
+
+        let duplicate = names("a/IMG_1234(1).jpg");
+        assert!(duplicate.contains(&"a/IMG_1234.jpg(1).json".to_string()));
+        assert!(names("a/IMG_1234-edited.jpg").contains(&"a/IMG_1234.jpg.json".to_string()));
+
+        let long = names("a/PXL_20250306_054142125.PORTRAIT.ORIGINAL_longer.jpg");
+        assert!(
+            long.contains(&"a/PXL_20250306_054142125.PORTRAIT.ORIGINAL_longe.json".to_string())
+        );
+    }
+}
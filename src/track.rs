@@ -0,0 +1,355 @@
+//! GPS tracks recorded alongside a video, such as GoPro GPMF telemetry or DJI flight
+//! logs. A track is written as its own LineString feature/placemark next to the video's
+//! Point, under the same name. Per-point times and relative altitudes, where the log
+//! has them, go into a KML `gx:Track` and the GeoJSON `coordinateProperties`.
+use crate::file_location::{property_string, xml_escaped};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrackPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+    /// Metres above the take-off point, from drone flight logs
+    #[serde(default)]
+    pub relative_altitude: Option<f64>,
+    /// Same format as `Track::start`
+    #[serde(default)]
+    pub time: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    pub points: Vec<TrackPoint>,
+    pub start: Option<String>, // RFC 3339; UTC, or local without an offset if the zone is unknown
+    pub end: Option<String>,   // Same as `start`
+}
+
+impl Track {
+    /// Per-point times only make a `gx:Track` if every point has one.
+    fn has_point_times(&self) -> bool {
+        !self.points.is_empty() && self.points.iter().all(|p| p.time.is_some())
+    }
+
+    /// In the `coordinateProperties` layout of togeojson and the mapping libraries that
+    /// read it: one array per property, aligned with the coordinates.
+    fn coordinate_properties(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut properties = serde_json::Map::new();
+        if self.points.iter().any(|p| p.time.is_some()) {
+            let times = self.points.iter().map(|p| json!(p.time)).collect();
+            properties.insert("times".to_string(), serde_json::Value::Array(times));
+        }
+        if self.points.iter().any(|p| p.relative_altitude.is_some()) {
+            let altitudes = self
+                .points
+                .iter()
+                .map(|p| json!(p.relative_altitude))
+                .collect();
+            properties.insert(
+                "relative_altitudes".to_string(),
+                serde_json::Value::Array(altitudes),
+            );
+        }
+        properties
+    }
+
+    pub fn as_geojson(&self, name: &str) -> String {
+        let coordinates: Vec<serde_json::Value> = self
+            .points
+            .iter()
+            .map(|p| match p.altitude {
+                Some(altitude) => json!([p.longitude, p.latitude, altitude]),
+                None => json!([p.longitude, p.latitude]),
+            })
+            .collect();
+        let mut j = json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "LineString",
+                "coordinates": coordinates
+            },
+            "properties": {
+                "name": name,
+            }
+        });
+        if let Some(start) = &self.start {
+            j["properties"]["timestamp_start"] = json!(start);
+        }
+        if let Some(end) = &self.end {
+            j["properties"]["timestamp_end"] = json!(end);
+        }
+        let coordinate_properties = self.coordinate_properties();
+        if !coordinate_properties.is_empty() {
+            j["properties"]["coordinateProperties"] = json!(coordinate_properties);
+        }
+        j.to_string()
+    }
+
+    pub fn as_kml(&self, name: &str) -> String {
+        let mut time_span = String::new();
+        if self.start.is_some() || self.end.is_some() {
+            time_span += "<TimeSpan>";
+            if let Some(start) = &self.start {
+                time_span += &format!("<begin>{start}</begin>");
+            }
+            if let Some(end) = &self.end {
+                time_span += &format!("<end>{end}</end>");
+            }
+            time_span += "</TimeSpan>";
+        }
+        let geometry = if self.has_point_times() {
+            self.kml_gx_track()
+        } else {
+            self.kml_line_string()
+        };
+        format!(
+            "<Placemark><name>{}</name>{time_span}{geometry}</Placemark>",
+            xml_escaped(name),
+        )
+    }
+
+    fn kml_line_string(&self) -> String {
+        let coordinates: Vec<String> = self
+            .points
+            .iter()
+            .map(|p| match p.altitude {
+                Some(altitude) => format!("{},{},{altitude}", p.longitude, p.latitude),
+                None => format!("{},{}", p.longitude, p.latitude),
+            })
+            .collect();
+        format!(
+            "<LineString><coordinates>{}</coordinates></LineString>",
+            coordinates.join(" ")
+        )
+    }
+
+    /// A `gx:Track` has a `<when>` for each `<gx:coord>`, and per-point data as
+    /// `gx:SimpleArrayData` with a value for each, empty where a point has none.
+    fn kml_gx_track(&self) -> String {
+        let mut track = "<gx:Track>".to_string();
+        for point in &self.points {
+            track += &format!("<when>{}</when>", point.time.as_deref().unwrap_or_default());
+        }
+        for p in &self.points {
+            track += &match p.altitude {
+                Some(altitude) => format!(
+                    "<gx:coord>{} {} {altitude}</gx:coord>",
+                    p.longitude, p.latitude
+                ),
+                None => format!("<gx:coord>{} {}</gx:coord>", p.longitude, p.latitude),
+            };
+        }
+        if self.points.iter().any(|p| p.relative_altitude.is_some()) {
+            track += "<ExtendedData><SchemaData><gx:SimpleArrayData name=\"relative_altitude\">";
+            for p in &self.points {
+                let value = p.relative_altitude.map(|a| a.to_string());
+                track += &format!("<gx:value>{}</gx:value>", value.unwrap_or_default());
+            }
+            track += "</gx:SimpleArrayData></SchemaData></ExtendedData>";
+        }
+        track + "</gx:Track>"
+    }
+
+    /// Reads back a LineString feature written by `as_geojson`, with the name it belongs to.
+    pub fn from_geojson_feature(v: &serde_json::Value) -> Option<(String, Self)> {
+        if v["geometry"]["type"].as_str()? != "LineString" {
+            return None;
+        }
+        let properties = v["properties"].as_object()?;
+        let coordinate_property = |key: &str, i: usize| -> Option<&serde_json::Value> {
+            properties.get("coordinateProperties")?.get(key)?.get(i)
+        };
+        let points = v["geometry"]["coordinates"]
+            .as_array()?
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let c = c.as_array()?;
+                Some(TrackPoint {
+                    latitude: c.get(1)?.as_f64()?,
+                    longitude: c.first()?.as_f64()?,
+                    altitude: c.get(2).and_then(|a| a.as_f64()),
+                    relative_altitude: coordinate_property("relative_altitudes", i)
+                        .and_then(|a| a.as_f64()),
+                    time: coordinate_property("times", i)
+                        .and_then(|t| t.as_str())
+                        .map(|t| t.to_string()),
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let track = Self {
+            points,
+            start: property_string(properties, "timestamp_start"),
+            end: property_string(properties, "timestamp_end"),
+        };
+        Some((property_string(properties, "name")?, track))
+    }
+
+    /// Reads back a LineString or `gx:Track` placemark written by `as_kml`, with the
+    /// name it belongs to.
+    pub fn from_kml_placemark(pm: &kml::types::Placemark) -> Option<(String, Self)> {
+        let points = match &pm.geometry {
+            Some(kml::types::Geometry::LineString(line)) => line
+                .coords
+                .iter()
+                .map(|c| TrackPoint {
+                    latitude: c.y,
+                    longitude: c.x,
+                    altitude: c.z,
+                    ..Default::default()
+                })
+                .collect(),
+            // The KML crate has no type for it, so it is left as a plain element
+            None => Self::points_from_gx_track(pm.children.iter().find(|e| e.name == "Track")?)?,
+            _ => return None,
+        };
+        let time_span = pm.children.iter().find(|e| e.name == "TimeSpan");
+        let time = |name: &str| {
+            time_span?
+                .children
+                .iter()
+                .find(|e| e.name == name)?
+                .content
+                .clone()
+        };
+        let track = Self {
+            points,
+            start: time("begin"),
+            end: time("end"),
+        };
+        Some((pm.name.clone()?, track))
+    }
+
+    fn points_from_gx_track(track: &kml::types::Element) -> Option<Vec<TrackPoint>> {
+        let children = |name: &'static str| track.children.iter().filter(move |e| e.name == name);
+        let relative_altitudes: Vec<Option<f64>> = children("ExtendedData")
+            .flat_map(|e| e.children.iter())
+            .filter(|e| e.name == "SchemaData")
+            .flat_map(|e| e.children.iter())
+            .find(|e| {
+                e.name == "SimpleArrayData"
+                    && e.attrs
+                        .get("name")
+                        .is_some_and(|n| n == "relative_altitude")
+            })
+            .map(|e| {
+                e.children
+                    .iter()
+                    .map(|v| v.content.as_deref()?.trim().parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        children("coord")
+            .zip(children("when"))
+            .enumerate()
+            .map(|(i, (coord, when))| {
+                let coord: Vec<f64> = coord
+                    .content
+                    .as_deref()?
+                    .split_whitespace()
+                    .map(|c| c.parse().ok())
+                    .collect::<Option<_>>()?;
+                Some(TrackPoint {
+                    latitude: *coord.get(1)?,
+                    longitude: *coord.first()?,
+                    altitude: coord.get(2).copied(),
+                    relative_altitude: relative_altitudes.get(i).copied().flatten(),
+                    time: when.content.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kml::Kml;
+
+    fn track() -> Track {
+        Track {
+            points: vec![
+                TrackPoint {
+                    latitude: 45.5,
+                    longitude: 12.3,
+                    altitude: Some(10.5),
+                    ..Default::default()
+                },
+                TrackPoint {
+                    latitude: 45.6,
+                    longitude: 12.4,
+                    altitude: Some(11.0),
+                    ..Default::default()
+                },
+            ],
+            start: Some("2025-03-06T05:41:36Z".to_string()),
+            end: Some("2025-03-06T05:41:37.5Z".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_geojson_roundtrip() {
+        let v: serde_json::Value = serde_json::from_str(&track().as_geojson("a.mp4")).unwrap();
+        assert_eq!(
+            Track::from_geojson_feature(&v),
+            Some(("a.mp4".to_string(), track()))
+        );
+    }
+
+    #[test]
+    fn test_kml_roundtrip() {
+        let kml: Kml = track().as_kml("a&b.mp4").parse().unwrap();
+        let Kml::Placemark(pm) = kml else {
+            panic!("not a placemark: {kml:?}");
+        };
+        assert_eq!(
+            Track::from_kml_placemark(&pm),
+            Some(("a&b.mp4".to_string(), track()))
+        );
+    }
+
+    /// A DJI flight log, with a time for each point and a relative altitude for some
+    fn timed_track() -> Track {
+        let mut track = track();
+        track.points[0].time = Some("2025-03-06T05:41:36".to_string());
+        track.points[1].time = Some("2025-03-06T05:41:37.5".to_string());
+        track.points[1].relative_altitude = Some(30.1);
+        track.points[1].altitude = None;
+        track
+    }
+
+    #[test]
+    fn test_geojson_coordinate_properties() {
+        let v: serde_json::Value =
+            serde_json::from_str(&timed_track().as_geojson("a.srt")).unwrap();
+        assert_eq!(
+            v["properties"]["coordinateProperties"],
+            json!({
+                "times": ["2025-03-06T05:41:36", "2025-03-06T05:41:37.5"],
+                "relative_altitudes": [null, 30.1],
+            })
+        );
+        assert_eq!(
+            Track::from_geojson_feature(&v),
+            Some(("a.srt".to_string(), timed_track()))
+        );
+    }
+
+    #[test]
+    fn test_kml_gx_track() {
+        let kml = timed_track().as_kml("a.srt");
+        assert!(kml.contains("<gx:Track><when>2025-03-06T05:41:36</when><when>2025-03-06T05:41:37.5</when><gx:coord>12.3 45.5 10.5</gx:coord><gx:coord>12.4 45.6</gx:coord>"), "{kml}");
+        let Kml::Placemark(pm) = kml.parse().unwrap() else {
+            panic!("not a placemark: {kml}");
+        };
+        assert_eq!(
+            Track::from_kml_placemark(&pm),
+            Some(("a.srt".to_string(), timed_track()))
+        );
+        // Without a time for every point it stays a LineString
+        let mut track = timed_track();
+        track.points[0].time = None;
+        assert!(track.as_kml("a.srt").contains("<LineString>"));
+    }
+}
@@ -0,0 +1,220 @@
+use crate::datum::Datum;
+use crate::file_location::{FileLocation, UtcSource, property_string};
+use chrono::{DateTime, NaiveDateTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, json};
+
+/// A GPS fix this far from the capture time is likely a stale position from an
+/// earlier fix, when the capture time zone is known.
+const STALE_FIX_HOURS: i64 = 2;
+
+/// Without a known time zone, local capture time and UTC GPS time can legitimately be
+/// up to 14 hours apart, so only flag beyond that.
+const STALE_FIX_HOURS_NO_OFFSET: i64 = 14 + STALE_FIX_HOURS;
+
+/// Plausibility of a location, as classified by `validate`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "reason", rename_all = "lowercase")]
+pub enum Validity {
+    #[default]
+    Ok,
+    /// Probably usable, but worth checking
+    Suspicious(String),
+    /// Not a real position; excluded from the output by default
+    Invalid(String),
+}
+
+impl Validity {
+    pub fn is_invalid(&self) -> bool {
+        matches!(self, Self::Invalid(_))
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            Self::Ok => None,
+            Self::Suspicious(reason) | Self::Invalid(reason) => Some(reason),
+        }
+    }
+
+    fn status(&self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Suspicious(_) => "suspicious",
+            Self::Invalid(_) => "invalid",
+        }
+    }
+
+    /// `validity` and `validity_reason`, only for a location that failed a check, so
+    /// that unchecked output stays the same.
+    pub(crate) fn properties(&self) -> Vec<(&'static str, serde_json::Value)> {
+        match self.reason() {
+            Some(reason) => vec![
+                ("validity", json!(self.status())),
+                ("validity_reason", json!(reason)),
+            ],
+            None => vec![],
+        }
+    }
+
+    pub(crate) fn from_properties(properties: &Map<String, serde_json::Value>) -> Self {
+        let reason = property_string(properties, "validity_reason").unwrap_or_default();
+        match property_string(properties, "validity").as_deref() {
+            Some("suspicious") => Self::Suspicious(reason),
+            Some("invalid") => Self::Invalid(reason),
+            _ => Self::Ok,
+        }
+    }
+}
+
+/// Checks a freshly read location for the usual kinds of bogus GPS data.
+pub fn validate(fl: &FileLocation) -> Validity {
+    if !fl.latitude.is_finite() || !fl.longitude.is_finite() {
+        return Validity::Invalid("coordinates are not finite numbers".to_string());
+    }
+    if fl.latitude.abs() > 90.0 || fl.longitude.abs() > 180.0 {
+        return Validity::Invalid(format!(
+            "coordinates out of range ({}, {})",
+            fl.latitude, fl.longitude
+        ));
+    }
+    if fl.latitude == 0.0 && fl.longitude == 0.0 {
+        return Validity::Invalid("coordinates are exactly (0, 0)".to_string());
+    }
+    if let Some(accuracy) = &fl.accuracy
+        && accuracy
+            .status
+            .as_deref()
+            .is_some_and(|s| s.eq_ignore_ascii_case("V"))
+    {
+        return Validity::Invalid("GPS status is void".to_string());
+    }
+    if let Some(datum) = &fl.map_datum
+        && Datum::from_name(datum).is_none()
+    {
+        return Validity::Suspicious(format!(
+            "unknown map datum {datum:?}, coordinates not converted to WGS 84"
+        ));
+    }
+    if let Some(hours) = stale_fix_hours(fl) {
+        return Validity::Suspicious(format!(
+            "GPS fix is {hours:.1} hours away from the capture time"
+        ));
+    }
+    Validity::Ok
+}
+
+/// Returns the gap between GPS time and capture time in hours, if it is suspiciously large.
+fn stale_fix_hours(fl: &FileLocation) -> Option<f64> {
+    let gps = DateTime::parse_from_rfc3339(fl.timestamp_gps.as_ref()?)
+        .ok()?
+        .naive_utc();
+    let (capture, threshold): (NaiveDateTime, i64) = match fl.timestamp_utc_source {
+        Some(UtcSource::Offset) => (fl.timestamp_utc_parsed()?.naive_utc(), STALE_FIX_HOURS),
+        _ => (fl.timestamp_parsed()?, STALE_FIX_HOURS_NO_OFFSET),
+    };
+    let gap = (capture - gps).abs();
+    (gap > TimeDelta::hours(threshold)).then(|| gap.num_seconds() as f64 / 3600.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gps_accuracy::GpsAccuracy;
+
+    fn location(latitude: f64, longitude: f64) -> FileLocation {
+        FileLocation {
+            file: "x.jpg".to_string(),
+            latitude,
+            longitude,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate_coordinates() {
+        assert_eq!(validate(&location(45.5, 12.3)), Validity::Ok);
+        assert!(validate(&location(f64::NAN, 12.3)).is_invalid());
+        assert!(validate(&location(45.5, f64::INFINITY)).is_invalid());
+        assert!(validate(&location(91.0, 12.3)).is_invalid());
+        assert!(validate(&location(0.0, 0.0)).is_invalid());
+        assert_eq!(validate(&location(0.0, 12.3)), Validity::Ok);
+    }
+
+    #[test]
+    fn test_validate_void_status() {
+        let fl = FileLocation {
+            accuracy: Some(GpsAccuracy {
+                status: Some("V".to_string()),
+                ..Default::default()
+            }),
+            ..location(45.5, 12.3)
+        };
+        assert_eq!(
+            validate(&fl),
+            Validity::Invalid("GPS status is void".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_map_datum() {
+        let with_datum = |datum: &str| FileLocation {
+            map_datum: Some(datum.to_string()),
+            ..location(45.5, 12.3)
+        };
+        assert_eq!(validate(&with_datum("TOKYO")), Validity::Ok);
+        assert_eq!(
+            validate(&with_datum("PULKOVO 1942")),
+            Validity::Suspicious(
+                "unknown map datum \"PULKOVO 1942\", coordinates not converted to WGS 84"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_validate_stale_fix() {
+        let fl = FileLocation {
+            timestamp: Some("2025:03:06 12:00:00".to_string()),
+            timestamp_offset: Some("+01:00".to_string()),
+            timestamp_utc: Some("2025-03-06T11:00:00Z".to_string()),
+            timestamp_utc_source: Some(UtcSource::Offset),
+            timestamp_gps: Some("2025-03-06T10:30:00Z".to_string()),
+            ..location(45.5, 12.3)
+        };
+        assert_eq!(validate(&fl), Validity::Ok);
+
+        let stale = FileLocation {
+            timestamp_gps: Some("2025-03-06T07:00:00Z".to_string()),
+            ..fl.clone()
+        };
+        assert_eq!(
+            validate(&stale),
+            Validity::Suspicious("GPS fix is 4.0 hours away from the capture time".to_string())
+        );
+
+        // Without a time zone, a gap of a few hours may just be the UTC offset.
+        let no_offset = FileLocation {
+            timestamp_offset: None,
+            timestamp_utc: None,
+            timestamp_utc_source: None,
+            ..stale
+        };
+        assert_eq!(validate(&no_offset), Validity::Ok);
+    }
+
+    /// Files from other tools may have their own `validity`, or lack a reason
+    #[test]
+    fn test_from_properties() {
+        assert!(Validity::Ok.properties().is_empty());
+        let unknown = json!({"validity": "unknown", "validity_reason": "a"});
+        assert_eq!(
+            Validity::from_properties(unknown.as_object().unwrap()),
+            Validity::Ok
+        );
+        let no_reason = json!({"validity": "suspicious"});
+        assert_eq!(
+            Validity::from_properties(no_reason.as_object().unwrap()),
+            Validity::Suspicious(String::new())
+        );
+    }
+}
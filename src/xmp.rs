@@ -0,0 +1,661 @@
+//! Embedded XMP metadata, for images that carry their position only there. The packet is
+//! located in JPEG APP1, PNG `iTXt` and WebP `XMP ` chunks, the `XMLPacket` tag of TIFF
+//! and the TIFF-based raw formats, and the `mime` item of HEIF; its `exif:` GPS
+//! properties are turned back into an EXIF block so they take the same path as EXIF.
+//! Drone (`drone-dji:`) and panorama (`GPano:`) camera orientation has no EXIF
+//! equivalent and is read separately, as a `CameraPose`.
+use crate::heif;
+use crate::isobmff::{self, ByteReader};
+use chrono::{DateTime, NaiveDateTime};
+use exif::{Field, In, Rational, Tag, Value};
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+const JPEG_XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const PNG_XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+const MAX_PACKET_SIZE: u64 = 16 * 1024 * 1024;
+const TIFF_XMP_TAG: u16 = 700;
+
+/// Camera orientation and height that drones and 360° cameras write only to XMP.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct CameraPose {
+    pub direction: Option<f64>, // Degrees clockwise from north, 0 to 360
+    pub pitch: Option<f64>,     // Degrees, negative looking down
+    pub relative_altitude: Option<f64>, // Metres above the take-off point
+}
+
+/// The properties of an XMP packet, keyed by their qualified name as written (eg
+/// "exif:GPSLatitude"). Array properties (`rdf:Bag`, `rdf:Seq`, `rdf:Alt`) have one
+/// value per item; simple ones have a single value.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Xmp {
+    properties: HashMap<String, Vec<String>>,
+}
+
+impl Xmp {
+    /// Parses an XMP packet. Returns `None` if it is not well-formed XML.
+    pub(crate) fn parse(packet: &str) -> Option<Self> {
+        let mut reader = quick_xml::Reader::from_str(packet);
+        reader.config_mut().trim_text(true);
+        let mut ret = Self::default();
+        let mut open: Vec<String> = vec![];
+        loop {
+            match reader.read_event().ok()? {
+                Event::Start(e) => {
+                    ret.add_attributes(&e);
+                    open.push(qualified_name(&e));
+                }
+                Event::Empty(e) => ret.add_attributes(&e),
+                Event::End(_) => {
+                    open.pop();
+                }
+                Event::Text(text) => {
+                    // Text belongs to the innermost element that is not RDF structure
+                    if let Some(name) = open.iter().rev().find(|n| !is_structural(n)) {
+                        let text = text.unescape().ok()?.to_string();
+                        ret.properties.entry(name.clone()).or_default().push(text);
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        Some(ret)
+    }
+
+    fn add_attributes(&mut self, e: &BytesStart) {
+        for attr in e.attributes().flatten() {
+            let name = String::from_utf8_lossy(attr.key.as_ref()).to_string();
+            if is_structural(&name) || name.starts_with("xmlns") {
+                continue;
+            }
+            if let Ok(value) = attr.unescape_value() {
+                self.properties.insert(name, vec![value.to_string()]);
+            }
+        }
+    }
+
+    /// The value of a simple property. For arrays, this is the first item, which for
+    /// language alternatives (`rdf:Alt`) is the default language.
+    pub(crate) fn get(&self, name: &str) -> Option<&str> {
+        self.properties.get(name)?.first().map(|s| s.as_str())
+    }
+
+    /// All items of an array property.
+    pub(crate) fn get_all(&self, name: &str) -> &[String] {
+        self.properties.get(name).map_or(&[], |v| v.as_slice())
+    }
+
+    /// The camera pose. The gimbal yaw is the direction the camera points in; the
+    /// flight yaw, where the drone's nose points, is used if there is no gimbal.
+    pub(crate) fn camera_pose(&self) -> CameraPose {
+        let number = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| self.get(name)?.trim().parse::<f64>().ok())
+        };
+        CameraPose {
+            // DJI writes yaw from -180 to 180
+            direction: number(&[
+                "drone-dji:GimbalYawDegree",
+                "drone-dji:FlightYawDegree",
+                "GPano:PoseHeadingDegrees",
+            ])
+            .map(|yaw| yaw.rem_euclid(360.0)),
+            pitch: number(&["drone-dji:GimbalPitchDegree", "GPano:PosePitchDegrees"]),
+            relative_altitude: number(&["drone-dji:RelativeAltitude"]),
+        }
+    }
+
+    /// Converts the `exif:` GPS and capture time properties into a TIFF-format EXIF
+    /// block. Returns `None` if there is no position.
+    pub(crate) fn to_exif(&self) -> Option<Vec<u8>> {
+        let mut fields = vec![];
+        for (name, tag, ref_tag) in [
+            ("exif:GPSLatitude", Tag::GPSLatitude, Tag::GPSLatitudeRef),
+            ("exif:GPSLongitude", Tag::GPSLongitude, Tag::GPSLongitudeRef),
+        ] {
+            let (dms, hemisphere) = coordinate(self.get(name)?)?;
+            fields.push(field(tag, Value::Rational(dms)));
+            fields.push(field(ref_tag, ascii(&hemisphere.to_string())));
+        }
+        if let Some((altitude, negative)) = self.get("exif:GPSAltitude").and_then(signed_rational) {
+            fields.push(field(Tag::GPSAltitude, Value::Rational(vec![altitude])));
+            // Some applications write altitudes below sea level as negative numbers
+            let below_sea_level = negative || self.get("exif:GPSAltitudeRef") == Some("1");
+            fields.push(field(
+                Tag::GPSAltitudeRef,
+                Value::Byte(vec![below_sea_level as u8]),
+            ));
+        }
+        if let Some(direction) = self.get("exif:GPSImgDirection").and_then(rational) {
+            fields.push(field(
+                Tag::GPSImgDirection,
+                Value::Rational(vec![direction]),
+            ));
+            if let Some(r) = self.get("exif:GPSImgDirectionRef") {
+                fields.push(field(Tag::GPSImgDirectionRef, ascii(r)));
+            }
+        }
+        for (name, tag, ref_tag) in [
+            (
+                "exif:GPSDestLatitude",
+                Tag::GPSDestLatitude,
+                Tag::GPSDestLatitudeRef,
+            ),
+            (
+                "exif:GPSDestLongitude",
+                Tag::GPSDestLongitude,
+                Tag::GPSDestLongitudeRef,
+            ),
+        ] {
+            if let Some((dms, hemisphere)) = self.get(name).and_then(coordinate) {
+                fields.push(field(tag, Value::Rational(dms)));
+                fields.push(field(ref_tag, ascii(&hemisphere.to_string())));
+            }
+        }
+        for (name, tag, ref_name, ref_tag) in [
+            (
+                "exif:GPSDestBearing",
+                Tag::GPSDestBearing,
+                "exif:GPSDestBearingRef",
+                Tag::GPSDestBearingRef,
+            ),
+            (
+                "exif:GPSDestDistance",
+                Tag::GPSDestDistance,
+                "exif:GPSDestDistanceRef",
+                Tag::GPSDestDistanceRef,
+            ),
+            (
+                "exif:GPSSpeed",
+                Tag::GPSSpeed,
+                "exif:GPSSpeedRef",
+                Tag::GPSSpeedRef,
+            ),
+            (
+                "exif:GPSTrack",
+                Tag::GPSTrack,
+                "exif:GPSTrackRef",
+                Tag::GPSTrackRef,
+            ),
+        ] {
+            if let Some(value) = self.get(name).and_then(rational) {
+                fields.push(field(tag, Value::Rational(vec![value])));
+                if let Some(r) = self.get(ref_name) {
+                    fields.push(field(ref_tag, ascii(r)));
+                }
+            }
+        }
+        if let Some(datum) = self.get("exif:GPSMapDatum") {
+            fields.push(field(Tag::GPSMapDatum, ascii(datum)));
+        }
+        let captured = ["exif:DateTimeOriginal", "xmp:CreateDate"]
+            .iter()
+            .find_map(|name| self.get(name));
+        if let Some(captured) = captured {
+            fields.extend(capture_time_fields(captured));
+        }
+        if let Some(gps_time) = self.get("exif:GPSTimeStamp") {
+            fields.extend(gps_time_fields(gps_time));
+        }
+
+        let mut writer = exif::experimental::Writer::new();
+        for f in &fields {
+            writer.push_field(f);
+        }
+        let mut buf = Cursor::new(Vec::new());
+        writer.write(&mut buf, false).ok()?;
+        Some(buf.into_inner())
+    }
+}
+
+/// Reads the XMP packet embedded in a JPEG, PNG, WebP, TIFF, TIFF-based raw or HEIF
+/// file. CR3 and RAF files keep theirs elsewhere and are not read.
+pub(crate) fn xmp_from_image<R: Read + Seek>(reader: &mut R) -> Option<Xmp> {
+    let mut head = Vec::new();
+    reader.seek(SeekFrom::Start(0)).ok()?;
+    reader.by_ref().take(12).read_to_end(&mut head).ok()?;
+    let packet = match head.as_slice() {
+        [0xff, 0xd8, ..] => packet_from_jpeg(reader)?,
+        _ if head.starts_with(PNG_SIGNATURE) => packet_from_png(reader)?,
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P'] => packet_from_webp(reader)?,
+        [b'I', b'I', ..] => packet_from_tiff(reader, false)?,
+        [b'M', b'M', ..] => packet_from_tiff(reader, true)?,
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => heif::xmp_from_heif(reader)?,
+        _ => return None,
+    };
+    Xmp::parse(std::str::from_utf8(&packet).ok()?)
+}
+
+fn packet_from_jpeg<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    jpeg_segment(reader, 0xffe1, JPEG_XMP_HEADER)
+}
+
+/// The first JPEG segment with this marker whose body starts with `prefix`, without
+/// the prefix.
+pub(crate) fn jpeg_segment<R: Read + Seek>(
+    reader: &mut R,
+    wanted: u16,
+    prefix: &[u8],
+) -> Option<Vec<u8>> {
+    let mut pos = 2;
+    loop {
+        let header = isobmff::read_at(reader, pos, 4, 4).ok()?;
+        let mut r = ByteReader::new(&header);
+        let marker = r.u16()?;
+        let length = r.u16()? as u64;
+        // No metadata after the start of scan; stand-alone markers have no length
+        if marker == 0xffda || marker == 0xffd9 || marker >> 8 != 0xff || length < 2 {
+            return None;
+        }
+        if marker == wanted {
+            let segment = isobmff::read_at(reader, pos + 4, length - 2, MAX_PACKET_SIZE).ok()?;
+            if let Some(body) = segment.strip_prefix(prefix) {
+                return Some(body.to_vec());
+            }
+        }
+        pos += 2 + length;
+    }
+}
+
+fn packet_from_png<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    let mut pos = PNG_SIGNATURE.len() as u64;
+    loop {
+        let header = isobmff::read_at(reader, pos, 8, 8).ok()?;
+        let mut r = ByteReader::new(&header);
+        let length = r.u32()? as u64;
+        let chunk_type = r.array4()?;
+        match &chunk_type {
+            b"IEND" => return None,
+            b"iTXt" => {
+                let chunk = isobmff::read_at(reader, pos + 8, length, MAX_PACKET_SIZE).ok()?;
+                if let Some(packet) = packet_from_itxt(&chunk) {
+                    return Some(packet.to_vec());
+                }
+            }
+            _ => {}
+        }
+        pos += 12 + length; // Length, type, data and CRC
+    }
+}
+
+/// iTXt: keyword, NUL, compression flag and method, language tag, NUL, translated
+/// keyword, NUL, text. Compressed XMP is rare and not supported.
+fn packet_from_itxt(chunk: &[u8]) -> Option<&[u8]> {
+    let rest = chunk.strip_prefix(PNG_XMP_KEYWORD)?.strip_prefix(b"\0")?;
+    let (&[compressed, _method], rest) = rest.split_first_chunk::<2>()?;
+    if compressed != 0 {
+        return None;
+    }
+    let mut parts = rest.splitn(3, |b| *b == 0);
+    let (_language, _translated, text) = (parts.next()?, parts.next()?, parts.next()?);
+    Some(text)
+}
+
+fn packet_from_webp<R: Read + Seek>(reader: &mut R) -> Option<Vec<u8>> {
+    let mut pos = 12;
+    loop {
+        let header = isobmff::read_at(reader, pos, 8, 8).ok()?;
+        let length = u32::from_le_bytes(header[4..8].try_into().ok()?) as u64;
+        if &header[0..4] == b"XMP " {
+            return isobmff::read_at(reader, pos + 8, length, MAX_PACKET_SIZE).ok();
+        }
+        pos += 8 + length + length % 2; // Chunks are padded to an even size
+    }
+}
+
+/// The `XMLPacket` tag of the first IFD. The raw formats keep the TIFF byte order mark
+/// but some (ORF, RW2) have a magic number of their own, so that is not checked.
+fn packet_from_tiff<R: Read + Seek>(reader: &mut R, big_endian: bool) -> Option<Vec<u8>> {
+    let u16_at = |b: &[u8]| {
+        let b = [b[0], b[1]];
+        match big_endian {
+            true => u16::from_be_bytes(b),
+            false => u16::from_le_bytes(b),
+        }
+    };
+    let u32_at = |b: &[u8]| {
+        let b = [b[0], b[1], b[2], b[3]];
+        match big_endian {
+            true => u32::from_be_bytes(b),
+            false => u32::from_le_bytes(b),
+        }
+    };
+    let header = isobmff::read_at(reader, 0, 8, 8).ok()?;
+    let ifd = u32_at(&header[4..8]) as u64;
+    let count = u16_at(&isobmff::read_at(reader, ifd, 2, 2).ok()?) as u64;
+    let entries = isobmff::read_at(reader, ifd + 2, count * 12, 12 * u16::MAX as u64).ok()?;
+    // Tag, type (BYTE or UNDEFINED, so the count is the length), count, offset; a packet
+    // never fits into the four bytes that would hold it inline
+    let entry = entries
+        .chunks_exact(12)
+        .find(|e| u16_at(&e[0..2]) == TIFF_XMP_TAG && matches!(u16_at(&e[2..4]), 1 | 7))?;
+    let length = u32_at(&entry[4..8]) as u64;
+    if length <= 4 {
+        return None;
+    }
+    isobmff::read_at(
+        reader,
+        u32_at(&entry[8..12]) as u64,
+        length,
+        MAX_PACKET_SIZE,
+    )
+    .ok()
+}
+
+fn qualified_name(e: &BytesStart) -> String {
+    String::from_utf8_lossy(e.name().as_ref()).to_string()
+}
+
+/// RDF and XMP container elements, as opposed to actual properties.
+fn is_structural(name: &str) -> bool {
+    name.starts_with("rdf:") || name.starts_with("x:") || name == "xml:lang"
+}
+
+pub(crate) fn field(tag: Tag, value: Value) -> Field {
+    Field {
+        tag,
+        ifd_num: In::PRIMARY,
+        value,
+    }
+}
+
+pub(crate) fn ascii(s: &str) -> Value {
+    Value::Ascii(vec![s.as_bytes().to_vec()])
+}
+
+/// Parses "a/b" or a decimal number, keeping up to six decimals exact. `None` for
+/// negative numbers, which a RATIONAL cannot hold, see `signed_rational`.
+pub(crate) fn rational(s: &str) -> Option<Rational> {
+    match signed_rational(s)? {
+        (value, false) => Some(value),
+        (_, true) => None,
+    }
+}
+
+/// `rational` for numbers that may be negative, as their magnitude and whether they
+/// are. No GPS tag is an SRATIONAL; the sign goes into the `...Ref` tag instead, as for
+/// altitudes below sea level. Decimals that would overflow the 32-bit numerator, as
+/// for six decimals of 4295 or more, are dropped from the end; an integer part beyond
+/// `u32::MAX` is rejected.
+pub(crate) fn signed_rational(s: &str) -> Option<(Rational, bool)> {
+    let s = s.trim();
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let value = match s.split_once('/') {
+        Some((num, denom)) => {
+            Rational::from((num.trim().parse().ok()?, denom.trim().parse().ok()?))
+        }
+        None => decimal(s)?,
+    };
+    Some((value, negative && value.num != 0))
+}
+
+fn decimal(s: &str) -> Option<Rational> {
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+    if (int.is_empty() && frac.is_empty()) || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let int = match int {
+        "" => 0, // ".5"
+        int => int.parse::<u32>().ok()?,
+    };
+    let frac = &frac[..frac.len().min(6)];
+    (0..=frac.len()).rev().find_map(|decimals| {
+        let denom = 10u32.pow(decimals as u32);
+        let frac = match decimals {
+            0 => 0,
+            _ => frac[..decimals].parse::<u32>().ok()?,
+        };
+        let num = int.checked_mul(denom)?.checked_add(frac)?;
+        Some(Rational::from((num, denom)))
+    })
+}
+
+/// Parses an XMP GPS coordinate, "DDD,MM,SSk" or "DDD,MM.mmk", where k is the
+/// hemisphere letter.
+fn coordinate(s: &str) -> Option<(Vec<Rational>, char)> {
+    let s = s.trim();
+    let hemisphere = s.chars().last()?.to_ascii_uppercase();
+    if !"NSEW".contains(hemisphere) {
+        return None;
+    }
+    let parts = s[..s.len() - 1]
+        .split(',')
+        .map(rational)
+        .collect::<Option<Vec<_>>>()?;
+    match parts.len() {
+        2 | 3 => Some((parts, hemisphere)),
+        _ => None,
+    }
+}
+
+/// ISO 8601 capture time to `DateTimeOriginal`, `SubSecTimeOriginal` and
+/// `OffsetTimeOriginal`. XMP allows the time zone to be missing.
+fn capture_time_fields(s: &str) -> Vec<Field> {
+    let (local, offset) = match DateTime::parse_from_rfc3339(s) {
+        Ok(dt) => (dt.naive_local(), Some(dt.format("%:z").to_string())),
+        Err(_) => match NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f") {
+            Ok(dt) => (dt, None),
+            Err(_) => return vec![],
+        },
+    };
+    let mut ret = vec![field(
+        Tag::DateTimeOriginal,
+        ascii(&local.format("%Y:%m:%d %H:%M:%S").to_string()),
+    )];
+    let millis = local.and_utc().timestamp_subsec_millis();
+    if millis != 0 {
+        ret.push(field(
+            Tag::SubSecTimeOriginal,
+            ascii(&format!("{millis:03}")),
+        ));
+    }
+    if let Some(offset) = offset {
+        ret.push(field(Tag::OffsetTimeOriginal, ascii(&offset)));
+    }
+    ret
+}
+
+/// The XMP GPS time stamp is a full UTC date and time, split over two tags in EXIF.
+fn gps_time_fields(s: &str) -> Vec<Field> {
+    let Ok(dt) = DateTime::parse_from_rfc3339(s) else {
+        return vec![];
+    };
+    let dt = dt.naive_utc();
+    let time = dt.format("%H %M %S").to_string();
+    let time = time
+        .split(' ')
+        .filter_map(|p| p.parse::<u32>().ok())
+        .map(|p| Rational::from((p, 1)))
+        .collect();
+    vec![
+        field(Tag::GPSDateStamp, ascii(&dt.format("%Y:%m:%d").to_string())),
+        field(Tag::GPSTimeStamp, Value::Rational(time)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKET: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about="" xmlns:exif="http://ns.adobe.com/exif/1.0/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    exif:GPSLatitude="45,30.34N" exif:GPSLongitude="12,20.46967W"
+    exif:DateTimeOriginal="2025-03-06T05:41:42.125+01:00"
+    exif:GPSDestLatitude="45,30.4N" exif:GPSDestBearing="90/1" exif:GPSDestBearingRef="T">
+   <exif:GPSAltitude>4679/100</exif:GPSAltitude>
+   <exif:GPSAltitudeRef>1</exif:GPSAltitudeRef>
+   <exif:GPSSpeed>50/1</exif:GPSSpeed>
+   <exif:GPSSpeedRef>K</exif:GPSSpeedRef>
+   <dc:subject><rdf:Bag><rdf:li>sunrise</rdf:li><rdf:li>lagoon</rdf:li></rdf:Bag></dc:subject>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>"#;
+
+    #[test]
+    fn test_parse() {
+        let xmp = Xmp::parse(PACKET).unwrap();
+        assert_eq!(xmp.get("exif:GPSLatitude"), Some("45,30.34N"));
+        assert_eq!(xmp.get("exif:GPSAltitude"), Some("4679/100"));
+        assert_eq!(
+            xmp.properties["dc:subject"],
+            vec!["sunrise".to_string(), "lagoon".to_string()]
+        );
+        assert_eq!(xmp.get("rdf:about"), None);
+        assert_eq!(Xmp::parse("<a><b></a>"), None);
+    }
+
+    #[test]
+    fn test_to_exif() {
+        let tiff = Xmp::parse(PACKET).unwrap().to_exif().unwrap();
+        let exif = exif::Reader::new().read_raw(tiff).unwrap();
+        let get = |tag| {
+            exif.get_field(tag, In::PRIMARY)
+                .unwrap()
+                .display_value()
+                .to_string()
+        };
+        assert_eq!(get(Tag::GPSLatitude), "45/1, 3034/100");
+        assert_eq!(get(Tag::GPSLongitudeRef), "W");
+        assert_eq!(get(Tag::GPSAltitude), "46.79");
+        assert_eq!(get(Tag::GPSAltitudeRef), "below sea level");
+        assert_eq!(get(Tag::DateTimeOriginal), "2025-03-06 05:41:42");
+        assert_eq!(get(Tag::SubSecTimeOriginal), "\"125\"");
+        assert_eq!(get(Tag::OffsetTimeOriginal), "\"+01:00\"");
+        assert_eq!(get(Tag::GPSDestLatitude), "45/1, 304/10");
+        assert_eq!(get(Tag::GPSDestBearing), "90");
+        assert_eq!(get(Tag::GPSDestBearingRef), "true direction");
+        assert!(exif.get_field(Tag::GPSDestLongitude, In::PRIMARY).is_none());
+        assert_eq!(get(Tag::GPSSpeed), "50");
+        assert_eq!(get(Tag::GPSSpeedRef), "km/h");
+        assert!(exif.get_field(Tag::GPSTrack, In::PRIMARY).is_none());
+
+        let no_position = Xmp::parse("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/>").unwrap();
+        assert_eq!(no_position.to_exif(), None);
+    }
+
+    /// A negative altitude is below sea level even without the reference
+    #[test]
+    fn test_to_exif_signed_altitude() {
+        let packet = PACKET
+            .replace("4679/100", "-5000.123456")
+            .replace("<exif:GPSAltitudeRef>1</exif:GPSAltitudeRef>", "");
+        let tiff = Xmp::parse(&packet).unwrap().to_exif().unwrap();
+        let exif = exif::Reader::new().read_raw(tiff).unwrap();
+        let get = |tag| {
+            exif.get_field(tag, In::PRIMARY)
+                .unwrap()
+                .display_value()
+                .to_string()
+        };
+        assert_eq!(get(Tag::GPSAltitude), "5000.12345");
+        assert_eq!(get(Tag::GPSAltitudeRef), "below sea level");
+    }
+
+    #[test]
+    fn test_camera_pose() {
+        let drone = Xmp::parse(
+            r#"<rdf:Description xmlns:drone-dji="http://www.dji.com/drone-dji/1.0/"
+                drone-dji:GimbalYawDegree="-104.30" drone-dji:FlightYawDegree="-101.90"
+                drone-dji:GimbalPitchDegree="-45.00" drone-dji:RelativeAltitude="+30.10"/>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            drone.camera_pose(),
+            CameraPose {
+                direction: Some(255.7),
+                pitch: Some(-45.0),
+                relative_altitude: Some(30.1),
+            }
+        );
+        let pano = Xmp::parse(
+            r#"<rdf:Description xmlns:GPano="http://ns.google.com/photos/1.0/panorama/">
+                <GPano:PoseHeadingDegrees>90.5</GPano:PoseHeadingDegrees>
+               </rdf:Description>"#,
+        )
+        .unwrap();
+        assert_eq!(pano.camera_pose().direction, Some(90.5));
+        assert_eq!(pano.camera_pose().pitch, None);
+        assert_eq!(
+            Xmp::parse(PACKET).unwrap().camera_pose(),
+            CameraPose::default()
+        );
+    }
+
+    #[test]
+    fn test_rational_and_coordinate() {
+        let parts = |r: Rational| (r.num, r.denom);
+        assert_eq!(rational("12.5").map(parts), Some((125, 10)));
+        assert_eq!(rational("7/2").map(parts), Some((7, 2)));
+        assert!(rational("x").is_none());
+        assert!(rational("-12.5").is_none());
+        // Six decimals of 5000 overflow; fewer are kept
+        assert_eq!(
+            rational("5000.123456").map(parts),
+            Some((500012345, 100000))
+        );
+        assert_eq!(rational("4294967295.5").map(parts), Some((4294967295, 1)));
+        assert!(rational("4294967296").is_none());
+        assert_eq!(
+            signed_rational("-12.5").map(|(r, negative)| (parts(r), negative)),
+            Some(((125, 10), true))
+        );
+        assert_eq!(
+            signed_rational("-0").map(|(_, negative)| negative),
+            Some(false)
+        );
+        let (dms, hemisphere) = coordinate("45,30,20.4s").unwrap();
+        assert_eq!(hemisphere, 'S');
+        assert_eq!(
+            dms.into_iter().map(parts).collect::<Vec<_>>(),
+            [(45, 1), (30, 1), (204, 10)]
+        );
+        assert!(coordinate("45N").is_none());
+        assert!(coordinate("45,30X").is_none());
+    }
+
+    #[test]
+    fn test_packet_from_containers() {
+        let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe0, 0, 4, 0, 0, 0xff, 0xe1];
+        jpeg.extend(((JPEG_XMP_HEADER.len() + PACKET.len() + 2) as u16).to_be_bytes());
+        jpeg.extend(JPEG_XMP_HEADER);
+        jpeg.extend(PACKET.as_bytes());
+        jpeg.extend([0xff, 0xd9]);
+
+        let mut itxt = PNG_XMP_KEYWORD.to_vec();
+        itxt.extend(b"\0\0\0\0\0");
+        itxt.extend(PACKET.as_bytes());
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend((itxt.len() as u32).to_be_bytes());
+        png.extend(b"iTXt");
+        png.extend(&itxt);
+        png.extend([0; 4]);
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8L\x01\0\0\0\0\0XMP ".to_vec();
+        webp.extend((PACKET.len() as u32).to_le_bytes());
+        webp.extend(PACKET.as_bytes());
+
+        // Little-endian TIFF with one IFD entry, XMLPacket as BYTE, after the IFD
+        let mut tiff = b"II*\0\x08\0\0\0\x01\0".to_vec();
+        tiff.extend(TIFF_XMP_TAG.to_le_bytes());
+        tiff.extend(1u16.to_le_bytes());
+        tiff.extend((PACKET.len() as u32).to_le_bytes());
+        tiff.extend(26u32.to_le_bytes());
+        tiff.extend([0; 4]); // No next IFD
+        tiff.extend(PACKET.as_bytes());
+
+        for data in [jpeg, png, webp, tiff] {
+            let xmp = xmp_from_image(&mut Cursor::new(data)).unwrap();
+            assert_eq!(xmp.get("exif:GPSLongitude"), Some("12,20.46967W"));
+        }
+        assert_eq!(xmp_from_image(&mut Cursor::new(b"GIF89a".to_vec())), None);
+    }
+}